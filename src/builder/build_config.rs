@@ -1,12 +1,22 @@
+use crate::builder::build_metadata::BuildMetadata;
 use crate::builder::build_step::BuildStep;
-use crate::bundler::bundle_step::BundleStep;
-use crate::js_builder::JSBuildStep;
+use crate::builder::config_reader::{child, ConfigReader, BOOLEAN};
+use crate::bundler::bundle_step::{
+    BundleEntrypoint, BundleStep, CircularDependencyMode, RequireMode, RuntimeMode,
+    DEFAULT_SEARCH_PATH,
+};
+use crate::bundler::parser::LuaVersion;
+use crate::exec_runner::ExecStep;
+use crate::js_builder::{InstallOptions, JSBuildStep};
 use crate::logger;
 use crate::manifest_generator::ManifestGenerationStep;
 use crate::parallel_builder::ParallelBuildStep;
+use crate::process;
 use relative_path::RelativePathBuf;
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 pub const BUILD_CONFIG_FILE: &str = "jade.xml";
 
@@ -16,220 +26,436 @@ pub struct BuildConfig {
     steps: Vec<Box<dyn BuildStep>>,
 }
 
+// <timeout> is in seconds
+fn parse_timeout(reader: &mut ConfigReader, node: &roxmltree::Node) -> Option<Duration> {
+    let n = child(node, "timeout")?;
+    let text = reader.text(&n)?;
+    let seconds = reader.parse::<f64>(&n, text, "a number of seconds")?;
+    // zero, negative, NaN and too large values
+    match Duration::try_from_secs_f64(seconds) {
+        Ok(timeout) if !timeout.is_zero() => Some(timeout),
+        _ => {
+            reader.error(
+                &n,
+                format!("expected a positive number of seconds, found \"{}\"", text),
+            );
+            None
+        }
+    }
+}
+
+// <env name="..">value</env> children, `{env}` and the other placeholders are expanded
+fn parse_env(
+    reader: &mut ConfigReader,
+    node: &roxmltree::Node,
+    metadata: &BuildMetadata,
+) -> Vec<(String, String)> {
+    node.children()
+        .filter(|n| n.tag_name().name() == "env")
+        .map(|n| {
+            (
+                reader.attribute(&n, "name").unwrap_or_default().to_string(),
+                metadata.expand(n.text().unwrap_or_default()),
+            )
+        })
+        .collect()
+}
+
 fn parse_js_build(
+    reader: &mut ConfigReader,
     node: &roxmltree::Node,
     path: &PathBuf,
-    default_package_manager: &String,
+    default_package_manager: Option<&String>,
+    install_options: InstallOptions,
+    metadata: &BuildMetadata,
 ) -> Box<dyn BuildStep> {
-    let name = node.attribute("name").unwrap();
-    let folder = node
-        .children()
-        .find(|n| n.tag_name().name() == "folder")
-        .unwrap()
-        .text()
-        .unwrap();
-    let build_script = node
-        .children()
-        .find(|n| n.tag_name().name() == "build_script")
-        .unwrap()
-        .text()
-        .unwrap();
-    let install_packages = match node
-        .children()
-        .find(|n| n.tag_name().name() == "install_packages")
-    {
-        None => true,
-        Some(n) => n.text().unwrap() == "true",
-    };
-    let package_manager = match node
-        .children()
-        .find(|n| n.tag_name().name() == "package_manager")
-    {
-        None => default_package_manager,
-        Some(n) => n.text().unwrap(),
+    let name = reader.attribute(node, "name").unwrap_or_default();
+    let folder = reader.required_text(node, "folder").unwrap_or_default();
+    let build_script = reader
+        .required_text(node, "build_script")
+        .unwrap_or_default();
+    let install_packages = reader
+        .child_value(node, "install_packages", &BOOLEAN)
+        .unwrap_or(true);
+    let package_manager = match reader.optional_text(node, "package_manager") {
+        None => default_package_manager.map(|package_manager| package_manager.as_str()),
+        Some(package_manager) => Some(package_manager),
     };
+    let env = parse_env(reader, node, metadata);
+    let env_file = reader.optional_text(node, "env_file").map(|env_file| {
+        RelativePathBuf::from(metadata.expand(env_file))
+            .normalize()
+            .to_logical_path(path)
+    });
+    let timeout = parse_timeout(reader, node);
 
     Box::new(JSBuildStep {
         name: name.to_string(),
         build_script: build_script.to_string(),
-        package_manager: package_manager.to_string(),
+        package_manager: package_manager.map(|package_manager| package_manager.to_string()),
         install_packages,
+        install_options,
+        env,
+        env_file,
+        metadata: metadata.clone(),
+        timeout,
         folder: RelativePathBuf::from(folder)
             .normalize()
             .to_logical_path(path),
     })
 }
 
-fn parse_bundle(node: &roxmltree::Node, path: &PathBuf) -> Box<dyn BuildStep> {
-    let name = node.attribute("name").unwrap();
-    let entrypoint = node
-        .children()
-        .find(|n| n.tag_name().name() == "entrypoint")
-        .unwrap()
-        .text()
-        .unwrap();
-    let source_dir = node
-        .children()
-        .find(|n| n.tag_name().name() == "source_dir")
-        .unwrap()
-        .text()
-        .unwrap();
-    let output = node
+fn parse_exec(
+    reader: &mut ConfigReader,
+    node: &roxmltree::Node,
+    path: &PathBuf,
+    metadata: &BuildMetadata,
+) -> Box<dyn BuildStep> {
+    let name = reader.attribute(node, "name").unwrap_or_default();
+    let command = reader.required_text(node, "command").unwrap_or_default();
+    let args = match child(node, "args") {
+        None => Vec::new(),
+        Some(n) => n
+            .children()
+            .filter(|n| n.tag_name().name() == "arg")
+            .map(|n| metadata.expand(n.text().unwrap_or_default()))
+            .collect(),
+    };
+    let working_dir = match reader.optional_text(node, "working_dir") {
+        None => path.clone(),
+        Some(working_dir) => RelativePathBuf::from(working_dir)
+            .normalize()
+            .to_logical_path(path),
+    };
+    let mut env = metadata.environment_variables();
+    env.extend(parse_env(reader, node, metadata));
+    let timeout = parse_timeout(reader, node);
+    let exit_codes = match child(node, "exit_codes") {
+        None => vec![0],
+        Some(n) => n
+            .children()
+            .filter(|n| n.tag_name().name() == "exit_code")
+            .filter_map(|n| {
+                let text = reader.text(&n)?;
+                reader.parse::<i32>(&n, text, "an exit code")
+            })
+            .collect(),
+    };
+
+    Box::new(ExecStep {
+        name: name.to_string(),
+        command: command.to_string(),
+        args,
+        working_dir,
+        env,
+        timeout,
+        exit_codes,
+    })
+}
+
+fn parse_bundle(
+    reader: &mut ConfigReader,
+    node: &roxmltree::Node,
+    path: &PathBuf,
+    metadata: &BuildMetadata,
+) -> Box<dyn BuildStep> {
+    let name = reader.attribute(node, "name").unwrap_or_default();
+    let entrypoints = match (child(node, "entrypoints"), child(node, "entrypoint")) {
+        (None, None) => {
+            reader.error(node, "expected an <entrypoint> or <entrypoints> element");
+            Vec::new()
+        }
+        (None, Some(n)) => vec![BundleEntrypoint {
+            path: RelativePathBuf::from(reader.text(&n).unwrap_or_default())
+                .normalize()
+                .to_logical_path(path)
+                .with_extension("lua"),
+            output: None,
+        }],
+        (Some(n), _) => n
+            .children()
+            .filter(|n| n.tag_name().name() == "entrypoint")
+            .map(|n| BundleEntrypoint {
+                path: RelativePathBuf::from(reader.text(&n).unwrap_or_default())
+                    .normalize()
+                    .to_logical_path(path)
+                    .with_extension("lua"),
+                output: n.attribute("output").map(|output| {
+                    RelativePathBuf::from(output)
+                        .normalize()
+                        .to_logical_path(path)
+                        .with_extension("lua")
+                }),
+            })
+            .collect(),
+    };
+    let source_dir = reader.required_text(node, "source_dir").unwrap_or_default();
+    let output = reader.required_text(node, "output").unwrap_or_default();
+    let search_path = reader
+        .optional_text(node, "search_path")
+        .unwrap_or(DEFAULT_SEARCH_PATH);
+    let circular_dependencies = reader
+        .child_value(
+            node,
+            "circular_dependencies",
+            &[
+                ("error", CircularDependencyMode::Error),
+                ("warn", CircularDependencyMode::Warn),
+            ],
+        )
+        .unwrap_or(CircularDependencyMode::Error);
+    let debug = reader.child_value(node, "debug", &BOOLEAN).unwrap_or(false);
+    let (minify, rename_locals) = match child(node, "minify") {
+        None => (false, false),
+        Some(n) => (
+            reader
+                .attribute(&n, "enable")
+                .and_then(|enable| reader.value(&n, enable, &BOOLEAN))
+                .unwrap_or(false),
+            n.attribute("rename_locals")
+                .and_then(|rename_locals| reader.value(&n, rename_locals, &BOOLEAN))
+                .unwrap_or(false),
+        ),
+    };
+    let report_unused = reader
+        .child_value(node, "report_unused", &BOOLEAN)
+        .unwrap_or(false);
+    let (runtime, require_mode) = match child(node, "runtime") {
+        None => (RuntimeMode::Lazy, RequireMode::Global),
+        Some(n) => (
+            n.attribute("mode")
+                .and_then(|mode| {
+                    reader.value(
+                        &n,
+                        mode,
+                        &[
+                            ("lazy", RuntimeMode::Lazy),
+                            ("eager", RuntimeMode::Eager),
+                            ("dev", RuntimeMode::Dev),
+                        ],
+                    )
+                })
+                .unwrap_or(RuntimeMode::Lazy),
+            n.attribute("require")
+                .and_then(|require| {
+                    reader.value(
+                        &n,
+                        require,
+                        &[
+                            ("global", RequireMode::Global),
+                            ("local", RequireMode::Local),
+                        ],
+                    )
+                })
+                .unwrap_or(RequireMode::Global),
+        ),
+    };
+    let (bytecode, strip_debug_info) = match child(node, "bytecode") {
+        None => (false, false),
+        Some(n) => (
+            reader
+                .attribute(&n, "enable")
+                .and_then(|enable| reader.value(&n, enable, &BOOLEAN))
+                .unwrap_or(false),
+            n.attribute("strip_debug_info")
+                .and_then(|strip_debug_info| reader.value(&n, strip_debug_info, &BOOLEAN))
+                .unwrap_or(false),
+        ),
+    };
+    let banner = child(node, "banner").map(|n| metadata.expand(n.text().unwrap_or("")));
+    let build_info = reader
+        .child_value(node, "build_info", &BOOLEAN)
+        .unwrap_or(false)
+        .then(|| metadata.clone());
+    // cfx is 5.4 with the syntax extensions of FiveM, so it is the default
+    let lua_version = reader
+        .child_value(
+            node,
+            "lua_version",
+            &[
+                ("5.1", LuaVersion::Lua51),
+                ("5.4", LuaVersion::Lua54),
+                ("cfx", LuaVersion::Cfx),
+            ],
+        )
+        .unwrap_or(LuaVersion::Cfx);
+    // <define> can be written on the bundle itself or inside <defines>, a define without a
+    // value is "true"
+    let defines: HashMap<String, String> = node
         .children()
-        .find(|n| n.tag_name().name() == "output")
-        .unwrap()
-        .text()
-        .unwrap();
+        .chain(
+            child(node, "defines")
+                .into_iter()
+                .flat_map(|n| n.children()),
+        )
+        .filter(|n| n.tag_name().name() == "define")
+        .map(|n| {
+            let value = n.text().map(str::trim).filter(|value| !value.is_empty());
+            (
+                reader
+                    .attribute(&n, "name")
+                    .unwrap_or_default()
+                    .trim()
+                    .to_string(),
+                value.unwrap_or("true").to_string(),
+            )
+        })
+        .collect();
+    let externals = reader
+        .list(node, "externals", "external")
+        .into_iter()
+        .map(|external| external.to_string())
+        .collect();
 
     Box::new(BundleStep {
         name: name.to_string(),
-        entrypoint: RelativePathBuf::from(entrypoint)
-            .normalize()
-            .to_logical_path(path)
-            .with_extension("lua"),
-        source_dir: RelativePathBuf::from(source_dir)
-            .normalize()
-            .to_logical_path(path),
+        entrypoints,
+        // like package.path, several source dirs can be given separated by ';'
+        source_dirs: source_dir
+            .split(';')
+            .map(|dir| {
+                RelativePathBuf::from(dir.trim())
+                    .normalize()
+                    .to_logical_path(path)
+            })
+            .collect(),
+        search_path: search_path
+            .split(';')
+            .map(|template| template.trim().to_string())
+            .collect(),
         output: RelativePathBuf::from(output)
             .normalize()
             .to_logical_path(path)
             .with_extension("lua"),
+        circular_dependencies,
+        debug,
+        minify,
+        rename_locals,
+        lua_version,
+        report_unused,
+        runtime,
+        require_mode,
+        bytecode,
+        strip_debug_info,
+        banner,
+        build_info,
+        defines,
+        externals,
+        resource_path: path.clone(),
     })
 }
 
-fn parse_manifest(node: &roxmltree::Node, path: &PathBuf) -> Box<dyn BuildStep> {
+fn parse_manifest(
+    reader: &mut ConfigReader,
+    node: &roxmltree::Node,
+    path: &PathBuf,
+) -> Box<dyn BuildStep> {
+    let mut optional_text = |name: &str| {
+        reader
+            .optional_text(node, name)
+            .map(|text| text.to_string())
+    };
+    let author = optional_text("author");
+    let description = optional_text("description");
+    let version = optional_text("version");
+    let loadscreen = optional_text("loadscreen");
+    let ui_page = optional_text("ui_page");
+    let rdr3_warning = optional_text("rdr3_warning");
+    let mut list = |list: &str, item: &str| {
+        reader
+            .list(node, list, item)
+            .into_iter()
+            .map(|text| text.to_string())
+            .collect()
+    };
+    let client_scripts = list("client_scripts", "client_script");
+    let server_scripts = list("server_scripts", "server_script");
+    let shared_scripts = list("shared_scripts", "shared_script");
+    let dependencies = list("dependencies", "dependency");
+    let files = list("files", "file");
+    let mut enabled = |name: &str| match child(node, name) {
+        None => false,
+        Some(n) => reader
+            .attribute(&n, "enable")
+            .and_then(|enable| reader.value(&n, enable, &BOOLEAN))
+            .unwrap_or(false),
+    };
+    let is_a_map = enabled("is_a_map");
+    let lua54 = enabled("lua54");
+
     Box::new(ManifestGenerationStep {
         path: RelativePathBuf::from("./fxmanifest.lua")
             .normalize()
             .to_logical_path(path)
             .with_extension("lua"),
-        fx_version: node
-            .children()
-            .find(|n| n.tag_name().name() == "fx_version")
-            .unwrap()
-            .text()
-            .unwrap()
+        fx_version: reader
+            .required_text(node, "fx_version")
+            .unwrap_or_default()
             .to_string(),
-        game: node
-            .children()
-            .find(|n| n.tag_name().name() == "game")
-            .unwrap()
-            .text()
-            .unwrap()
+        game: reader
+            .required_text(node, "game")
+            .unwrap_or_default()
             .to_string(),
-        author: match node.children().find(|n| n.tag_name().name() == "author") {
-            None => None,
-            Some(n) => Some(n.text().unwrap().to_string()),
-        },
-        description: match node
-            .children()
-            .find(|n| n.tag_name().name() == "description")
-        {
-            None => None,
-            Some(n) => Some(n.text().unwrap().to_string()),
-        },
-        version: match node.children().find(|n| n.tag_name().name() == "version") {
-            None => None,
-            Some(n) => Some(n.text().unwrap().to_string()),
-        },
-        client_scripts: match node
-            .children()
-            .find(|n| n.tag_name().name() == "client_scripts")
-        {
-            None => Vec::new(),
-            Some(n) => n
-                .children()
-                .filter(|n| n.tag_name().name() == "client_script")
-                .map(|n| n.text().unwrap().to_string())
-                .collect(),
-        },
-        server_scripts: match node
-            .children()
-            .find(|n| n.tag_name().name() == "server_scripts")
-        {
-            None => Vec::new(),
-            Some(n) => n
-                .children()
-                .filter(|n| n.tag_name().name() == "server_script")
-                .map(|n| n.text().unwrap().to_string())
-                .collect(),
-        },
-        shared_scripts: match node
-            .children()
-            .find(|n| n.tag_name().name() == "shared_scripts")
-        {
-            None => Vec::new(),
-            Some(n) => n
-                .children()
-                .filter(|n| n.tag_name().name() == "shared_script")
-                .map(|n| n.text().unwrap().to_string())
-                .collect(),
-        },
-        dependencies: match node
-            .children()
-            .find(|n| n.tag_name().name() == "dependencies")
-        {
-            None => Vec::new(),
-            Some(n) => n
-                .children()
-                .filter(|n| n.tag_name().name() == "dependency")
-                .map(|n| n.text().unwrap().to_string())
-                .collect(),
-        },
-        files: match node.children().find(|n| n.tag_name().name() == "files") {
-            None => Vec::new(),
-            Some(n) => n
-                .children()
-                .filter(|n| n.tag_name().name() == "file")
-                .map(|n| n.text().unwrap().to_string())
-                .collect(),
-        },
-        loadscreen: match node
-            .children()
-            .find(|n| n.tag_name().name() == "loadscreen")
-        {
-            None => None,
-            Some(n) => Some(n.text().unwrap().to_string()),
-        },
-        ui_page: match node.children().find(|n| n.tag_name().name() == "ui_page") {
-            None => None,
-            Some(n) => Some(n.text().unwrap().to_string()),
-        },
-        is_a_map: match node.children().find(|n| n.tag_name().name() == "is_a_map") {
-            None => false,
-            Some(n) => n.attribute("enable").unwrap() == "true",
-        },
-        lua54: match node.children().find(|n| n.tag_name().name() == "lua54") {
-            None => false,
-            Some(n) => n.attribute("enable").unwrap() == "true",
-        },
-        rdr3_warning: match node
-            .children()
-            .find(|n| n.tag_name().name() == "rdr3_warning")
-        {
-            None => None,
-            Some(n) => Some(n.text().unwrap().to_string()),
-        },
+        author,
+        description,
+        version,
+        client_scripts,
+        server_scripts,
+        shared_scripts,
+        dependencies,
+        files,
+        loadscreen,
+        ui_page,
+        is_a_map,
+        lua54,
+        rdr3_warning,
     })
 }
 
+// steps with mistakes in their config are still built from placeholder values, BuildConfig::new
+// throws them away once the reader has errors
 fn parse_steps(
+    reader: &mut ConfigReader,
     root: &roxmltree::Node,
     path: &PathBuf,
-    package_manager: &String,
+    package_manager: Option<&String>,
+    install_options: InstallOptions,
+    metadata: &BuildMetadata,
 ) -> Vec<Box<dyn BuildStep>> {
     root.children()
+        .filter(|node| node.is_element())
         .filter_map(|node| match node.tag_name().name() {
-            "js_build" => Some(parse_js_build(&node, path, package_manager)),
-            "bundle" => Some(parse_bundle(&node, path)),
-            "manifest" => Some(parse_manifest(&node, path)),
+            "js_build" => Some(parse_js_build(
+                reader,
+                &node,
+                path,
+                package_manager,
+                install_options,
+                metadata,
+            )),
+            "exec" => Some(parse_exec(reader, &node, path, metadata)),
+            "bundle" => Some(parse_bundle(reader, &node, path, metadata)),
+            "manifest" => Some(parse_manifest(reader, &node, path)),
             "parallel" => Some(Box::new(ParallelBuildStep {
-                steps: parse_steps(&node, path, package_manager)
-                    .into_iter()
-                    .map(|step| Arc::new(step))
-                    .collect(),
+                steps: parse_steps(
+                    reader,
+                    &node,
+                    path,
+                    package_manager,
+                    install_options,
+                    metadata,
+                )
+                .into_iter()
+                .map(|step| Arc::new(step))
+                .collect(),
             })),
-            _ => None,
+            _ => {
+                reader.error(
+                    &node,
+                    "is not a build step, expected <js_build>, <exec>, <bundle>, <manifest> or <parallel>",
+                );
+                None
+            }
         })
         .collect()
 }
@@ -238,14 +464,20 @@ impl BuildConfig {
     pub fn new(
         name: String,
         build_file_path: PathBuf,
-        package_manager: &String,
+        package_manager: Option<&String>,
+        install_options: InstallOptions,
+        environment: Option<&String>,
     ) -> Result<BuildConfig, ()> {
         let resource_path = PathBuf::from(build_file_path.parent().unwrap());
 
+        let mut config_file = build_file_path.clone();
         let build_config_string = match std::fs::read_to_string(&build_file_path) {
             Ok(string) => string,
             Err(_) => match std::fs::read_to_string(resource_path.join(BUILD_CONFIG_FILE)) {
-                Ok(string) => string,
+                Ok(string) => {
+                    config_file = resource_path.join(BUILD_CONFIG_FILE);
+                    string
+                }
                 Err(_) => {
                     logger::log_error(
                         format!(
@@ -261,11 +493,12 @@ impl BuildConfig {
 
         let build_config = match roxmltree::Document::parse(&build_config_string) {
             Ok(config) => config,
-            Err(_) => {
+            Err(error) => {
                 logger::log_error(
                     format!(
-                        "Failed to parse build config file {}",
-                        build_file_path.display()
+                        "Failed to parse build config file {}: {}",
+                        config_file.display(),
+                        error
                     )
                     .as_str(),
                 );
@@ -273,11 +506,84 @@ impl BuildConfig {
             }
         };
 
+        let manifest_version = build_config
+            .descendants()
+            .find(|n| n.tag_name().name() == "manifest")
+            .and_then(|n| n.children().find(|n| n.tag_name().name() == "version"))
+            .and_then(|n| n.text())
+            .map(|version| version.to_string());
+        let metadata = BuildMetadata::collect(&name, &resource_path, manifest_version, environment);
+
+        let mut reader = ConfigReader::new(config_file.clone());
+        let root = build_config.root_element();
+        if root.tag_name().name() != "config" {
+            reader.error(
+                &root,
+                "is not a build config, expected <config> as the root element",
+            );
+        }
         let steps: Vec<Box<dyn BuildStep>> = parse_steps(
-            &build_config.root_element(),
+            &mut reader,
+            &root,
             &resource_path,
             package_manager,
+            install_options,
+            &metadata,
         );
+        if !reader.errors.is_empty() {
+            for error in &reader.errors {
+                logger::log_error(error.to_string().as_str());
+            }
+            logger::log_error(
+                format!(
+                    "[{}] Found {} error(s) in {}",
+                    name,
+                    reader.errors.len(),
+                    config_file.display()
+                )
+                .as_str(),
+            );
+            return Err(());
+        }
+
+        // lua 5.4 bytecode can only be loaded by resources that run on lua 5.4
+        let lua54 = match steps.iter().flat_map(|step| step.manifest_steps()).next() {
+            Some(manifest) => manifest.lua54,
+            None => std::fs::read_to_string(resource_path.join("fxmanifest.lua")).is_ok_and(
+                |manifest| {
+                    manifest.lines().any(|line| {
+                        let line = line.trim();
+                        line == r#"lua54 "yes""# || line == "lua54 'yes'"
+                    })
+                },
+            ),
+        };
+        for bundle in steps.iter().flat_map(|step| step.bundle_steps()) {
+            if !bundle.bytecode {
+                continue;
+            }
+            if !lua54 {
+                logger::log_error(
+                    format!(
+                        "[{}/{}] Bytecode output needs lua54 enabled in the manifest",
+                        name, &bundle.name
+                    )
+                    .as_str(),
+                );
+                return Err(());
+            }
+            // the cfx syntax extensions are reported by the compiler, as it only knows lua 5.4
+            if bundle.lua_version == LuaVersion::Lua51 {
+                logger::log_error(
+                    format!(
+                        "[{}/{}] Bytecode output is only supported for lua 5.4",
+                        name, &bundle.name
+                    )
+                    .as_str(),
+                );
+                return Err(());
+            }
+        }
 
         if steps.is_empty() {
             logger::log_warn(
@@ -288,14 +594,33 @@ impl BuildConfig {
         Ok(BuildConfig { name, steps })
     }
 
-    pub fn build(&self) {
+    pub fn bundle_steps(&self) -> Vec<&BundleStep> {
+        self.steps
+            .iter()
+            .flat_map(|step| step.bundle_steps())
+            .collect()
+    }
+
+    // stops at the first step that fails, the steps after it are skipped
+    pub fn build(&self) -> Result<(), ()> {
         let start_time = std::time::Instant::now();
         logger::log_info(format!("[{}] Starting build", &self.name).as_str());
-        let _ = &self.steps.iter().for_each(|step| step.build(&self.name));
+        for step in &self.steps {
+            // steps that did not start yet are skipped once the build is cancelled
+            if process::is_cancelled() || step.build(&self.name).is_err() {
+                if process::is_cancelled() {
+                    logger::log_warn(format!("[{}] Build cancelled", &self.name).as_str());
+                } else {
+                    logger::log_error(format!("[{}] Build failed", &self.name).as_str());
+                }
+                return Err(());
+            }
+        }
         let duration = start_time.elapsed().as_secs_f64();
         logger::log_success(
             format!("[{}] Built successfully in {:.2}s", &self.name, duration).as_str(),
         );
+        Ok(())
     }
 }
 
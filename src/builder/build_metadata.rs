@@ -0,0 +1,124 @@
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// information about the build that steps can embed in their output
+#[derive(Debug, Clone)]
+pub struct BuildMetadata {
+    pub resource: String,
+    pub version: Option<String>,
+    pub commit: Option<String>,
+    // UTC, in ISO 8601 format
+    pub timestamp: String,
+    pub environment: Option<String>,
+}
+
+// the short hash of the commit checked out in the repository that contains `path`
+fn git_commit(path: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(path)
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+// reads `version "x"` from a manifest that is not generated by jade
+fn manifest_version(path: &Path) -> Option<String> {
+    let manifest = std::fs::read_to_string(path.join("fxmanifest.lua")).ok()?;
+    manifest.lines().find_map(|line| {
+        let value = line.trim().strip_prefix("version")?.trim();
+        let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        Some(value.trim_matches(quote).to_string())
+    })
+}
+
+// formats seconds since the unix epoch as "YYYY-MM-DDTHH:MM:SSZ"
+fn format_timestamp(seconds: u64) -> String {
+    let (days, time) = (seconds / 86400, seconds % 86400);
+    // converts days since the epoch to a civil date, see
+    // https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+impl BuildMetadata {
+    // `version` is the version of the manifest jade generates, if there is one
+    pub fn collect(
+        resource: &str,
+        path: &Path,
+        version: Option<String>,
+        environment: Option<&String>,
+    ) -> BuildMetadata {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        BuildMetadata {
+            resource: resource.to_string(),
+            version: version.or_else(|| manifest_version(path)),
+            commit: git_commit(path),
+            timestamp: format_timestamp(now),
+            environment: environment.cloned(),
+        }
+    }
+
+    // the values placeholders like `{resource}` are replaced with, missing values are None
+    pub fn fields(&self) -> Vec<(&str, Option<&str>)> {
+        vec![
+            ("resource", Some(self.resource.as_str())),
+            ("version", self.version.as_deref()),
+            ("commit", self.commit.as_deref()),
+            ("timestamp", Some(self.timestamp.as_str())),
+            // builds without --env use the default config
+            (
+                "env",
+                Some(self.environment.as_deref().unwrap_or("default")),
+            ),
+        ]
+    }
+
+    // passed to every process a step runs
+    pub fn environment_variables(&self) -> Vec<(String, String)> {
+        vec![
+            (
+                "JADE_ENV".to_string(),
+                self.environment.as_deref().unwrap_or("default").to_string(),
+            ),
+            ("JADE_RESOURCE".to_string(), self.resource.clone()),
+        ]
+    }
+
+    pub fn expand(&self, template: &str) -> String {
+        self.fields()
+            .into_iter()
+            .fold(template.to_string(), |text, (name, value)| {
+                text.replace(format!("{{{}}}", name).as_str(), value.unwrap_or("unknown"))
+            })
+    }
+}
@@ -1,6 +1,19 @@
+use crate::bundler::bundle_step::BundleStep;
+use crate::manifest_generator::ManifestGenerationStep;
 use std::fmt::Debug;
 
 pub trait BuildStep: Debug + Send + Sync {
-    // the resource_name is only passed for logging purposes
-    fn build(&self, resource_name: &String);
+    // the resource_name is only passed for logging purposes. Errors are logged by the step itself,
+    // an Err only tells the caller to stop building the resource
+    fn build(&self, resource_name: &String) -> Result<(), ()>;
+
+    // the bundle and manifest steps contained in this step, used to inspect and validate a
+    // config without building it
+    fn bundle_steps(&self) -> Vec<&BundleStep> {
+        Vec::new()
+    }
+
+    fn manifest_steps(&self) -> Vec<&ManifestGenerationStep> {
+        Vec::new()
+    }
 }
@@ -0,0 +1,156 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use roxmltree::Node;
+
+pub const BOOLEAN: [(&str, bool); 4] =
+    [("true", true), ("false", false), ("1", true), ("0", false)];
+
+// a mistake in a build config, positions are 1-based like in editors
+#[derive(Debug)]
+pub struct ConfigError {
+    pub file: PathBuf,
+    pub line: u32,
+    pub column: u32,
+    pub element: String,
+    pub expected: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: <{}> {}",
+            self.file.display(),
+            self.line,
+            self.column,
+            self.element,
+            self.expected
+        )
+    }
+}
+
+// reads values out of a build config, mistakes are collected instead of stopping at the first
+// one so a config can be fixed in one go. The methods return None when they reported an error
+#[derive(Debug)]
+pub struct ConfigReader {
+    file: PathBuf,
+    pub errors: Vec<ConfigError>,
+}
+
+pub fn child<'a, 'input>(node: &Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.tag_name().name() == name)
+}
+
+// "a", "b" or "c"
+fn describe(values: &[&str]) -> String {
+    let quoted: Vec<String> = values
+        .iter()
+        .map(|value| format!("\"{}\"", value))
+        .collect();
+    match quoted.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} or {}", rest.join(", "), last),
+        _ => quoted.join(""),
+    }
+}
+
+impl ConfigReader {
+    pub fn new(file: PathBuf) -> ConfigReader {
+        ConfigReader {
+            file,
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn error(&mut self, node: &Node, expected: impl Into<String>) {
+        let position = node.document().text_pos_at(node.range().start);
+        self.errors.push(ConfigError {
+            file: self.file.clone(),
+            line: position.row,
+            column: position.col,
+            element: node.tag_name().name().to_string(),
+            expected: expected.into(),
+        });
+    }
+
+    pub fn attribute<'a>(&mut self, node: &Node<'a, '_>, name: &str) -> Option<&'a str> {
+        let value = node.attribute(name);
+        if value.is_none() {
+            self.error(node, format!("expected the \"{}\" attribute", name));
+        }
+        value
+    }
+
+    pub fn text<'a>(&mut self, node: &Node<'a, '_>) -> Option<&'a str> {
+        let text = node.text().filter(|text| !text.trim().is_empty());
+        if text.is_none() {
+            self.error(node, "expected a value, but the element is empty");
+        }
+        text
+    }
+
+    pub fn required_text<'a>(&mut self, node: &Node<'a, '_>, name: &str) -> Option<&'a str> {
+        match child(node, name) {
+            Some(n) => self.text(&n),
+            None => {
+                self.error(node, format!("expected a <{}> element", name));
+                None
+            }
+        }
+    }
+
+    // None if the element is missing, an error if it is there but empty
+    pub fn optional_text<'a>(&mut self, node: &Node<'a, '_>, name: &str) -> Option<&'a str> {
+        child(node, name).and_then(|n| self.text(&n))
+    }
+
+    // the text of every `item` inside the `list` element, e.g. <files><file>..</file></files>
+    pub fn list<'a>(&mut self, node: &Node<'a, '_>, list: &str, item: &str) -> Vec<&'a str> {
+        match child(node, list) {
+            None => Vec::new(),
+            Some(n) => n
+                .children()
+                .filter(|n| n.tag_name().name() == item)
+                .filter_map(|n| self.text(&n))
+                .collect(),
+        }
+    }
+
+    // `text` must be one of the names in `values`
+    pub fn value<T: Copy>(&mut self, node: &Node, text: &str, values: &[(&str, T)]) -> Option<T> {
+        let value = values
+            .iter()
+            .find(|(name, _)| *name == text.trim())
+            .map(|(_, value)| *value);
+        if value.is_none() {
+            let names: Vec<&str> = values.iter().map(|(name, _)| *name).collect();
+            self.error(
+                node,
+                format!("expected {}, found \"{}\"", describe(&names), text),
+            );
+        }
+        value
+    }
+
+    // None if the element is missing
+    pub fn child_value<T: Copy>(
+        &mut self,
+        node: &Node,
+        name: &str,
+        values: &[(&str, T)],
+    ) -> Option<T> {
+        let n = child(node, name)?;
+        let text = self.text(&n)?;
+        self.value(&n, text, values)
+    }
+
+    // `expected` describes the value, e.g. "a number of seconds"
+    pub fn parse<T: FromStr>(&mut self, node: &Node, text: &str, expected: &str) -> Option<T> {
+        let value = text.trim().parse::<T>().ok();
+        if value.is_none() {
+            self.error(node, format!("expected {}, found \"{}\"", expected, text));
+        }
+        value
+    }
+}
@@ -1,2 +1,4 @@
 pub mod build_config;
+pub mod build_metadata;
 pub mod build_step;
+pub mod config_reader;
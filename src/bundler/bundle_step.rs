@@ -1,26 +1,134 @@
 use super::bundler::Bundler;
-use crate::{builder::build_step::BuildStep, logger};
+use super::bytecode;
+use super::parser::LuaVersion;
+use crate::{
+    builder::{build_metadata::BuildMetadata, build_step::BuildStep},
+    logger,
+};
 use std::{
-    fs::{create_dir_all, File},
+    collections::HashMap,
+    fs::{create_dir_all, write},
     path::PathBuf,
 };
 
+pub const DEFAULT_SEARCH_PATH: &str = "?.lua;?/init.lua;?.json;?.txt";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CircularDependencyMode {
+    Error,
+    Warn,
+}
+
+// when bundled modules are initialised at runtime
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RuntimeMode {
+    // on their first require
+    Lazy,
+    // all of them when the bundle loads, dependencies first
+    Eager,
+    // lazily, and `__jade_reload(module)` reloads a module from its source file
+    Dev,
+}
+
+// whether the bundle replaces the global `require` or only shadows it inside the bundle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RequireMode {
+    Global,
+    Local,
+}
+
+#[derive(Debug)]
+pub struct BundleEntrypoint {
+    pub path: PathBuf,
+    // when set, the entrypoint and the modules only it requires are written to their own file
+    pub output: Option<PathBuf>,
+}
+
 #[derive(Debug)]
 pub struct BundleStep {
     pub name: String,
     pub output: PathBuf,
-    pub source_dir: PathBuf,
-    pub entrypoint: PathBuf,
+    pub source_dirs: Vec<PathBuf>,
+    pub search_path: Vec<String>,
+    pub entrypoints: Vec<BundleEntrypoint>,
+    pub circular_dependencies: CircularDependencyMode,
+    pub debug: bool,
+    pub minify: bool,
+    pub rename_locals: bool,
+    // the lua dialect the sources are checked against
+    pub lua_version: LuaVersion,
+    // warn about unreachable files in the source dirs and unused requires
+    pub report_unused: bool,
+    pub runtime: RuntimeMode,
+    pub require_mode: RequireMode,
+    // write lua 5.4 bytecode instead of source, needs `lua54 "yes"` in the manifest
+    pub bytecode: bool,
+    pub strip_debug_info: bool,
+    // replaces the default header of every output file, placeholders are already expanded
+    pub banner: Option<String>,
+    // when set, the bundle defines a global `__JADE_BUILD` table with this data
+    pub build_info: Option<BuildMetadata>,
+    pub defines: HashMap<String, String>,
+    // glob patterns of modules that are provided at runtime and must not be bundled
+    pub externals: Vec<String>,
+    pub resource_path: PathBuf,
 }
 
 impl BuildStep for BundleStep {
-    fn build(&self, resource_name: &String) {
+    fn build(&self, resource_name: &String) -> Result<(), ()> {
         logger::log_info(format!("[{}/{}] Bundling lua", resource_name, &self.name).as_str());
-        let bundler = Bundler::new(self);
+        let bundler = match Bundler::new(self, resource_name) {
+            Ok(bundler) => bundler,
+            Err(_) => {
+                logger::log_error(
+                    format!("[{}/{}] Failed to bundle lua", resource_name, &self.name).as_str(),
+                );
+                return Err(());
+            }
+        };
+
+        if self.report_unused {
+            bundler.report_unused();
+        }
 
-        create_dir_all(&self.output.parent().unwrap()).unwrap();
-        let mut out_file = File::create(&self.output).unwrap();
+        for (output, contents) in bundler.render() {
+            let contents = match self.bytecode {
+                false => contents.into_bytes(),
+                true => {
+                    let chunk_name = format!(
+                        "@{}/{}",
+                        resource_name,
+                        output
+                            .strip_prefix(&self.resource_path)
+                            .unwrap_or(&output)
+                            .to_string_lossy()
+                            .replace('\\', "/")
+                    );
+                    match bytecode::compile(&contents, &chunk_name, self.strip_debug_info) {
+                        Ok(bytecode) => bytecode,
+                        Err(error) => {
+                            logger::log_error(
+                                format!(
+                                    "[{}/{}] Failed to compile {}: {}",
+                                    resource_name,
+                                    &self.name,
+                                    output.display(),
+                                    error
+                                )
+                                .as_str(),
+                            );
+                            return Err(());
+                        }
+                    }
+                }
+            };
+            create_dir_all(output.parent().unwrap()).unwrap();
+            write(&output, contents).unwrap();
+        }
+        Ok(())
+    }
 
-        bundler.write_bundle(&mut out_file);
+    fn bundle_steps(&self) -> Vec<&BundleStep> {
+        vec![self]
     }
 }
@@ -1,99 +1,1187 @@
-use super::bundle_step::BundleStep;
-use core::panic;
-use regex::Regex;
+use super::bundle_step::{BundleStep, CircularDependencyMode, RequireMode, RuntimeMode};
+use super::graph::{GraphNode, ModuleGraph};
+use super::lexer::{self, Token, TokenKind};
+use super::literals::{json_to_lua, to_long_string, to_lua_string};
+use super::minifier;
+use super::parser::{self, LuaVersion};
+use super::preprocessor;
+use crate::builder::build_metadata::BuildMetadata;
+use crate::logger;
 use relative_path::RelativePathBuf;
-use std::{collections::HashMap, fs::File, io::Write, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
 
 const FILE_SEPARATOR: &str = ".";
-const BUNDLE_BOILERPLATE: &str = "--[[Generated with Jade (https://github.com/JaRoLoz)]]
-local ____bundle__dict, ____bundle__cache = {}, {}
-require = function(module)
+const TEMPLATE_MARK: &str = "?";
+const BUNDLE_HEADER: &str = "--[[Generated with Jade (https://github.com/JaRoLoz)]]\n";
+const BUNDLE_BOILERPLATE: &str =
+    "local ____bundle__dict, ____bundle__cache, ____bundle__require = {}, {}, require
+local function __jade_require(module)
     if ____bundle__cache[module] then
         return ____bundle__cache[module]
     end
+    if not ____bundle__dict[module] then
+        return ____bundle__require(module)
+    end
     local module_func = ____bundle__dict[module]()
     ____bundle__cache[module] = module_func
     return module_func
 end
 ";
+const GLOBAL_REQUIRE: &str = "require = __jade_require\n";
+// the chunks loaded in debug and dev mode can not see the locals of the bundle, so they get an
+// environment of their own that resolves `require` to the bundle
+const LOCAL_REQUIRE: &str = "local require = __jade_require
+local ____bundle__env = setmetatable({ require = __jade_require }, { __index = _G, __newindex = _G })
+";
+// reloaded sources are read as they are in the resource folder, so defines are not applied to
+// them. On the client the files must be listed in the manifest for LoadResourceFile to find them
+const DEV_RUNTIME: &str = "local ____bundle__paths = {}
+__jade_reload = function(module)
+    local path = ____bundle__paths[module]
+    if not path then
+        error((\"module '%s' is not part of the bundle\"):format(module), 2)
+    end
+    local resource = GetCurrentResourceName()
+    local source = LoadResourceFile(resource, path)
+    if not source then
+        error((\"could not read '%s'\"):format(path), 2)
+    end
+    if path:sub(-5) == \".json\" then
+        ____bundle__dict[module] = function() return json.decode(source) end
+    elseif path:sub(-4) == \".txt\" then
+        ____bundle__dict[module] = function() return source end
+    else
+        ____bundle__dict[module] = assert({load})
+    end
+    ____bundle__cache[module] = nil
+    return __jade_require(module)
+end
+";
+// gives an entrypoint that shares the chunk with others a scope of its own
+const SHARED_ENTRYPOINT: &str = "do
+local ____bundle__entrypoint = function(...)
+{source}
+end
+____bundle__entrypoint(...)
+end
+";
+
+enum RequireCall {
+    Literal { module_name: String, line: usize },
+    Dynamic { line: usize, column: usize },
+}
 
 #[derive(Debug, Clone)]
 struct DependencyNode {
     path: PathBuf,
+    // the preprocessed source of the module
+    contents: String,
+    // bundled modules required by this node, in the order they first appear
+    requires: Vec<String>,
+}
+
+struct Entrypoint {
+    // the entrypoint is not a module, so it gets a name that a require can never produce
+    name: String,
+    node: DependencyNode,
+    output: Option<PathBuf>,
+}
+
+// one entry of the require chain that is currently being scanned, `line` is the line of the
+// require that leads to the next entry
+#[derive(Debug, Clone)]
+struct RequireFrame {
+    module_name: String,
+    path: PathBuf,
+    line: usize,
+}
+
+struct ScanState<'a> {
+    defines: &'a HashMap<String, String>,
+    source_dirs: &'a [PathBuf],
+    search_path: &'a [String],
+    externals: &'a [String],
+    // external modules with the place they were first required from
+    found_externals: Vec<RequireFrame>,
+    errors: Vec<String>,
+    // only logged when the bundle builds, like the externals
+    warnings: Vec<String>,
+    stack: Vec<RequireFrame>,
+    cycles: Vec<Vec<RequireFrame>>,
 }
 
 pub struct Bundler {
-    main_node: Option<DependencyNode>,
+    entrypoints: Vec<Entrypoint>,
     modules: HashMap<String, DependencyNode>,
+    output: PathBuf,
+    // global the shared chunk publishes its runtime under, so the entrypoint chunks can register
+    // their own modules into it
+    registry_name: String,
+    runtime: RuntimeMode,
+    require_mode: RequireMode,
+    header: String,
+    build_info: Option<BuildMetadata>,
+    debug: bool,
+    minify: bool,
+    rename_locals: bool,
+    lua_version: LuaVersion,
+    source_dirs: Vec<PathBuf>,
+    search_path: Vec<String>,
+    resource_name: String,
+    resource_path: PathBuf,
+    log_prefix: String,
 }
 
 impl DependencyNode {
-    pub fn new(path: PathBuf) -> DependencyNode {
-        DependencyNode { path }
+    pub fn load(
+        path: PathBuf,
+        defines: &HashMap<String, String>,
+    ) -> Result<DependencyNode, String> {
+        let source = match std::fs::read_to_string(&path) {
+            Ok(source) => source,
+            Err(error) => return Err(format!("Failed to read {}: {}", path.display(), error)),
+        };
+        // json and text assets are turned into modules that return their contents
+        let contents = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => match json::parse(&source) {
+                Ok(value) => format!("return {}\n", json_to_lua(&value)),
+                Err(error) => return Err(format!("Failed to parse {}: {}", path.display(), error)),
+            },
+            Some("txt") => format!("return {}\n", to_long_string(&source)),
+            _ => match preprocessor::preprocess(&source, defines) {
+                Ok(contents) => contents,
+                Err(error) => {
+                    return Err(format!(
+                        "Failed to preprocess {}:{}: {}",
+                        path.display(),
+                        error.line,
+                        error.message
+                    ))
+                }
+            },
+        };
+        Ok(DependencyNode {
+            path,
+            contents,
+            requires: Vec::new(),
+        })
+    }
+
+    pub fn get_contents(&self) -> &str {
+        &self.contents
     }
 
-    pub fn get_contents(&self) -> String {
-        std::fs::read_to_string(&self.path).unwrap()
+    fn add_require(&mut self, module_name: &str) {
+        if !self.requires.iter().any(|name| name == module_name) {
+            self.requires.push(module_name.to_string());
+        }
     }
 
-    pub fn scan_dependencies(
-        &self,
-        base_path: &PathBuf,
+    fn scan_dependencies(
+        &mut self,
+        own_name: &str,
         dependencies: &mut HashMap<String, DependencyNode>,
+        state: &mut ScanState,
     ) {
-        let require_regex = Regex::new(r#"require\("([^"]+)"\)"#).unwrap();
-        for capture in require_regex.captures_iter(self.get_contents().as_str()) {
-            let module_name = capture.get(1).unwrap().as_str();
+        // reported like the syntax check does, which skips errors that were already reported
+        let tokens = match lexer::tokenize(self.get_contents()) {
+            Ok(tokens) => tokens,
+            Err(error) => {
+                state.errors.push(format!(
+                    "Syntax error at {}:{}:{}: {}",
+                    self.path.display(),
+                    error.line,
+                    error.column,
+                    error.message
+                ));
+                return;
+            }
+        };
+
+        state.stack.push(RequireFrame {
+            module_name: own_name.to_string(),
+            path: self.path.clone(),
+            line: 0,
+        });
+
+        for require in find_require_calls(&tokens) {
+            let (module_name, line) = match require {
+                RequireCall::Literal { module_name, line } => (module_name, line),
+                RequireCall::Dynamic { line, column } => {
+                    state.warnings.push(format!(
+                        "Dynamic require at {}:{}:{} can not be bundled",
+                        self.path.display(),
+                        line,
+                        column
+                    ));
+                    continue;
+                }
+            };
+            let module_name = module_name.as_str();
+            state.stack.last_mut().unwrap().line = line;
+
+            if let Some(start) = state
+                .stack
+                .iter()
+                .position(|frame| frame.module_name == module_name)
+            {
+                state.cycles.push(state.stack[start..].to_vec());
+                self.add_require(module_name);
+                continue;
+            }
             if dependencies.contains_key(module_name) {
+                self.add_require(module_name);
+                continue;
+            }
+            if state
+                .externals
+                .iter()
+                .any(|pattern| matches_glob(pattern, module_name))
+            {
+                if !state
+                    .found_externals
+                    .iter()
+                    .any(|external| external.module_name == module_name)
+                {
+                    state.found_externals.push(RequireFrame {
+                        module_name: module_name.to_string(),
+                        path: self.path.clone(),
+                        line,
+                    });
+                }
                 continue;
             }
 
-            let module_path = RelativePathBuf::from(module_name.replace(FILE_SEPARATOR, "/"))
-                .normalize()
-                .to_logical_path(base_path)
-                .with_extension("lua");
+            let module_path = match state.resolve_module(module_name) {
+                Ok(module_path) => module_path,
+                Err(tried_paths) => {
+                    state.errors.push(format!(
+                        "Module not found: {} required at {}:{}, tried:\n    {}",
+                        module_name,
+                        self.path.display(),
+                        line,
+                        tried_paths
+                            .iter()
+                            .map(|path| path.display().to_string())
+                            .collect::<Vec<String>>()
+                            .join("\n    ")
+                    ));
+                    continue;
+                }
+            };
 
-            if !module_path.exists() {
-                panic!(
-                    "Module not found: {} ({})",
-                    module_name,
-                    module_path.display()
-                );
+            let mut module = match DependencyNode::load(module_path, state.defines) {
+                Ok(module) => module,
+                Err(error) => {
+                    state.errors.push(error);
+                    continue;
+                }
+            };
+            // modules are only stored once their scan is done, the ones still being scanned are
+            // on the stack
+            module.scan_dependencies(module_name, dependencies, state);
+            dependencies.insert(module_name.to_string(), module);
+            self.add_require(module_name);
+        }
+
+        state.stack.pop();
+    }
+}
+
+impl ScanState<'_> {
+    // tries every search path template in every source dir, in order, the same way lua walks
+    // `package.path`. On failure the list of tried paths is returned
+    fn resolve_module(&self, module_name: &str) -> Result<PathBuf, Vec<PathBuf>> {
+        let module_path = module_name.replace(FILE_SEPARATOR, "/");
+        let mut tried_paths = Vec::new();
+        for source_dir in self.source_dirs {
+            for template in self.search_path {
+                let path = RelativePathBuf::from(template.replace(TEMPLATE_MARK, &module_path))
+                    .normalize()
+                    .to_logical_path(source_dir);
+                if path.is_file() {
+                    return Ok(path);
+                }
+                tried_paths.push(path);
+            }
+        }
+        Err(tried_paths)
+    }
+}
+
+// `*` matches any run of characters (including '/' and '.') and `?` a single one
+fn matches_glob(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+fn format_cycle(cycle: &[RequireFrame]) -> String {
+    let mut chain: Vec<&str> = cycle
+        .iter()
+        .map(|frame| frame.module_name.as_str())
+        .collect();
+    chain.push(&cycle[0].module_name);
+
+    let mut report = format!("Circular dependency detected: {}", chain.join(" -> "));
+    for (i, frame) in cycle.iter().enumerate() {
+        report.push_str(
+            format!(
+                "\n    {} requires \"{}\" at {}:{}",
+                frame.module_name,
+                chain[i + 1],
+                frame.path.display(),
+                frame.line
+            )
+            .as_str(),
+        );
+    }
+    report
+}
+
+// a banner that is not already written as lua comments is wrapped in a long comment
+fn banner_comment(banner: &str) -> String {
+    let banner = banner.trim();
+    let is_comment = banner
+        .lines()
+        .all(|line| line.trim().is_empty() || line.trim_start().starts_with("--"));
+    match is_comment {
+        true => format!("{}\n", banner),
+        false => format!("--{}\n", to_long_string(banner)),
+    }
+}
+
+// files under `dir` and its subdirectories, sorted so reports are stable
+fn list_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|e| e.path())
+        .collect();
+    paths.sort();
+    for path in paths {
+        if path.is_dir() {
+            list_files(&path, files);
+        } else {
+            files.push(path);
+        }
+    }
+}
+
+// `local name = require("module")` statements whose local is never read afterwards, returned as
+// the module name and the line of the require
+fn find_unused_requires(source: &str, version: LuaVersion) -> Vec<(String, usize)> {
+    let Ok(tokens) = lexer::tokenize(source) else {
+        return Vec::new();
+    };
+    let tokens: Vec<Token> = tokens
+        .into_iter()
+        .filter(|token| token.kind != TokenKind::Comment)
+        .collect();
+    let Ok(info) = parser::parse(&tokens, version) else {
+        return Vec::new();
+    };
+
+    let mut unused = Vec::new();
+    for i in 0..tokens.len().saturating_sub(4) {
+        if !tokens[i].is_keyword("local")
+            || !tokens[i + 2].is_symbol("=")
+            || tokens[i + 3].text != "require"
+            || info.resolutions[i + 3].is_some()
+        {
+            continue;
+        }
+        let Some(binding) = info.resolutions[i + 1] else {
+            continue;
+        };
+        let module_name = match (&tokens[i + 4].kind, tokens.get(i + 5)) {
+            (TokenKind::String(module_name), _) => module_name,
+            (
+                _,
+                Some(Token {
+                    kind: TokenKind::String(module_name),
+                    ..
+                }),
+            ) if tokens[i + 4].is_symbol("(") => module_name,
+            _ => continue,
+        };
+        let is_read = info
+            .resolutions
+            .iter()
+            .enumerate()
+            .any(|(j, resolution)| j != i + 1 && *resolution == Some(binding));
+        if !is_read {
+            unused.push((module_name.clone(), tokens[i + 3].line));
+        }
+    }
+    unused
+}
+
+// walks the token stream looking for calls to the global `require`, the only forms that can be
+// resolved at bundle time are `require "x"`, `require [[x]]` and `require("x")`
+fn find_require_calls(tokens: &[Token]) -> Vec<RequireCall> {
+    let tokens: Vec<&Token> = tokens
+        .iter()
+        .filter(|token| token.kind != TokenKind::Comment)
+        .collect();
+
+    let mut calls = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        if token.kind != TokenKind::Name || token.text != "require" {
+            continue;
+        }
+        // `obj.require(...)`, `obj?.require(...)`, `obj:require(...)`, `local require` and
+        // `function require` are not calls to the global require
+        if i > 0 {
+            let previous = tokens[i - 1];
+            if previous.is_symbol(".")
+                || previous.is_symbol("?.")
+                || previous.is_symbol(":")
+                || previous.is_keyword("local")
+                || previous.is_keyword("function")
+            {
+                continue;
             }
+        }
 
-            let module = DependencyNode::new(module_path);
-            dependencies.insert(module_name.to_string(), module.clone());
-            module.scan_dependencies(base_path, dependencies);
+        let line = token.line;
+        match (tokens.get(i + 1), tokens.get(i + 2), tokens.get(i + 3)) {
+            (
+                Some(Token {
+                    kind: TokenKind::String(module_name),
+                    ..
+                }),
+                _,
+                _,
+            ) => {
+                calls.push(RequireCall::Literal {
+                    module_name: module_name.clone(),
+                    line,
+                });
+            }
+            (
+                Some(open),
+                Some(Token {
+                    kind: TokenKind::String(module_name),
+                    ..
+                }),
+                Some(close),
+            ) if open.is_symbol("(") && close.is_symbol(")") => {
+                calls.push(RequireCall::Literal {
+                    module_name: module_name.clone(),
+                    line,
+                });
+            }
+            (Some(open), _, _) if open.is_symbol("(") || open.is_symbol("{") => {
+                calls.push(RequireCall::Dynamic {
+                    line,
+                    column: token.column,
+                });
+            }
+            _ => {}
         }
     }
+    calls
 }
 
 impl Bundler {
-    pub fn new(config: &BundleStep) -> Bundler {
+    pub fn new(config: &BundleStep, resource_name: &str) -> Result<Bundler, ()> {
         let mut bundler = Bundler {
-            main_node: None,
+            entrypoints: Vec::new(),
             modules: HashMap::new(),
+            output: config.output.clone(),
+            registry_name: format!("____bundle:{}", &config.name),
+            runtime: config.runtime,
+            require_mode: config.require_mode,
+            header: match &config.banner {
+                None => BUNDLE_HEADER.to_string(),
+                Some(banner) => banner_comment(banner),
+            },
+            build_info: config.build_info.clone(),
+            debug: config.debug,
+            minify: config.minify,
+            rename_locals: config.rename_locals,
+            lua_version: config.lua_version,
+            source_dirs: config.source_dirs.clone(),
+            search_path: config.search_path.clone(),
+            resource_name: resource_name.to_string(),
+            resource_path: config.resource_path.clone(),
+            log_prefix: format!("[{}/{}]", resource_name, &config.name),
         };
 
-        let main_node = DependencyNode::new(config.entrypoint.clone());
-        main_node.scan_dependencies(&config.source_dir, &mut bundler.modules);
-        bundler.main_node = Some(main_node);
+        let mut state = ScanState {
+            defines: &config.defines,
+            source_dirs: &config.source_dirs,
+            search_path: &config.search_path,
+            externals: &config.externals,
+            found_externals: Vec::new(),
+            errors: Vec::new(),
+            warnings: Vec::new(),
+            stack: Vec::new(),
+            cycles: Vec::new(),
+        };
+        // every module is scanned once, even when several entrypoints require it
+        for entrypoint in &config.entrypoints {
+            let mut node = match DependencyNode::load(entrypoint.path.clone(), &config.defines) {
+                Ok(node) => node,
+                Err(error) => {
+                    state.errors.push(error);
+                    continue;
+                }
+            };
+            let name = format!("<{}>", bundler.relative_path(&entrypoint.path));
+            node.scan_dependencies(&name, &mut bundler.modules, &mut state);
+            bundler.entrypoints.push(Entrypoint {
+                name,
+                node,
+                output: entrypoint.output.clone(),
+            });
+        }
+
+        for error in bundler.check_syntax() {
+            if !state.errors.contains(&error) {
+                state.errors.push(error);
+            }
+        }
+        for error in &state.errors {
+            logger::log_error(format!("{} {}", &bundler.log_prefix, error).as_str());
+        }
+        if !state.errors.is_empty() {
+            return Err(());
+        }
+
+        for warning in &state.warnings {
+            logger::log_warn(format!("{} {}", &bundler.log_prefix, warning).as_str());
+        }
+        for external in &state.found_externals {
+            logger::log_info(
+                format!(
+                    "{} External module \"{}\" (first required at {}:{})",
+                    &bundler.log_prefix,
+                    external.module_name,
+                    external.path.display(),
+                    external.line
+                )
+                .as_str(),
+            );
+        }
+
+        for cycle in &state.cycles {
+            let message = format!("{} {}", &bundler.log_prefix, format_cycle(cycle));
+            match config.circular_dependencies {
+                CircularDependencyMode::Error => logger::log_error(message.as_str()),
+                CircularDependencyMode::Warn => logger::log_warn(message.as_str()),
+            }
+        }
+        if !state.cycles.is_empty() && config.circular_dependencies == CircularDependencyMode::Error
+        {
+            return Err(());
+        }
 
-        bundler
+        Ok(bundler)
     }
 
-    pub fn write_bundle(&self, out_file: &mut File) {
-        out_file.write_all(BUNDLE_BOILERPLATE.as_bytes()).unwrap();
-        for (name, module) in &self.modules {
-            let content = format!(
-                "____bundle__dict[\"{}\"] = function()\n{}\nend\n",
-                name,
-                module.get_contents()
+    // every module is parsed before anything is written, so all the broken files of a bundle are
+    // reported at once instead of failing at runtime
+    fn check_syntax(&self) -> Vec<String> {
+        let mut names: Vec<&String> = self.modules.keys().collect();
+        names.sort();
+        self.entrypoints
+            .iter()
+            .map(|entrypoint| &entrypoint.node)
+            .chain(names.into_iter().map(|name| &self.modules[name]))
+            .filter_map(|node| {
+                parser::check_syntax(node.get_contents(), self.lua_version)
+                    .err()
+                    .map(|error| {
+                        format!(
+                            "Syntax error at {}:{}:{}: {}",
+                            node.path.display(),
+                            error.line,
+                            error.column,
+                            error.message
+                        )
+                    })
+            })
+            .collect()
+    }
+
+    pub fn graph(&self, bundle_name: &str) -> ModuleGraph {
+        let all_requires: Vec<String> = self
+            .entrypoints
+            .iter()
+            .flat_map(|entrypoint| entrypoint.node.requires.clone())
+            .collect();
+        let nodes: Vec<(&String, &DependencyNode)> = self
+            .entrypoints
+            .iter()
+            .map(|entrypoint| (&entrypoint.name, &entrypoint.node))
+            .chain(
+                self.collect_modules(&all_requires, &mut HashSet::new())
+                    .into_iter()
+                    .map(|name| self.modules.get_key_value(&name).unwrap()),
+            )
+            .collect();
+
+        ModuleGraph {
+            bundle: bundle_name.to_string(),
+            nodes: nodes
+                .iter()
+                .map(|(name, node)| GraphNode {
+                    name: name.to_string(),
+                    path: self.relative_path(&node.path),
+                    size: node.get_contents().len(),
+                })
+                .collect(),
+            edges: nodes
+                .iter()
+                .flat_map(|(name, node)| {
+                    node.requires
+                        .iter()
+                        .map(|required| (name.to_string(), required.clone()))
+                })
+                .collect(),
+        }
+    }
+
+    // warns about files in the source dirs that no entrypoint reaches and about required modules
+    // whose value is never used
+    pub fn report_unused(&self) {
+        let reachable: HashSet<PathBuf> = self
+            .entrypoints
+            .iter()
+            .map(|entrypoint| &entrypoint.node)
+            .chain(self.modules.values())
+            .map(|node| &node.path)
+            // bundles written into a source dir must not be reported either
+            .chain(std::iter::once(&self.output))
+            .chain(self.entrypoints.iter().filter_map(|e| e.output.as_ref()))
+            .filter_map(|path| dunce::canonicalize(path).ok())
+            .collect();
+        // only files a require could resolve to are considered, e.g. `?.lua` matches `*.lua`
+        let templates: Vec<(&str, &str)> = self
+            .search_path
+            .iter()
+            .filter_map(|template| template.split_once(TEMPLATE_MARK))
+            .collect();
+
+        // source dirs may overlap, so every file is reported once
+        let mut reported = HashSet::new();
+        for source_dir in &self.source_dirs {
+            let mut files = Vec::new();
+            list_files(source_dir, &mut files);
+            for file in files {
+                let relative = file
+                    .strip_prefix(source_dir)
+                    .unwrap()
+                    .to_string_lossy()
+                    .replace('\\', "/");
+                let is_module = templates.iter().any(|(prefix, suffix)| {
+                    relative.len() > prefix.len() + suffix.len()
+                        && relative.starts_with(prefix)
+                        && relative.ends_with(suffix)
+                });
+                let Ok(path) = dunce::canonicalize(&file) else {
+                    continue;
+                };
+                if is_module && !reachable.contains(&path) && reported.insert(path) {
+                    logger::log_warn(
+                        format!(
+                            "{} Unused file {} is not reachable from any entrypoint",
+                            &self.log_prefix,
+                            self.relative_path(&file)
+                        )
+                        .as_str(),
+                    );
+                }
+            }
+        }
+
+        let mut names: Vec<&String> = self.modules.keys().collect();
+        names.sort();
+        let nodes = self
+            .entrypoints
+            .iter()
+            .map(|entrypoint| &entrypoint.node)
+            .chain(names.into_iter().map(|name| &self.modules[name]));
+        for node in nodes {
+            for (module_name, line) in find_unused_requires(node.get_contents(), self.lua_version) {
+                logger::log_warn(
+                    format!(
+                        "{} Module \"{}\" is required at {}:{} but never used",
+                        &self.log_prefix,
+                        module_name,
+                        self.relative_path(&node.path),
+                        line
+                    )
+                    .as_str(),
+                );
+            }
+        }
+    }
+
+    fn relative_path(&self, path: &Path) -> String {
+        path.strip_prefix(&self.resource_path)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/")
+    }
+
+    // modules reachable from the given requires, every module comes after its dependencies
+    fn collect_modules(&self, requires: &[String], visited: &mut HashSet<String>) -> Vec<String> {
+        let mut order = Vec::new();
+        for name in requires {
+            if visited.insert(name.clone()) {
+                order.extend(self.collect_modules(&self.modules[name].requires, visited));
+                order.push(name.clone());
+            }
+        }
+        order
+    }
+
+    // the chunk name follows the "@resource/path" convention fivem uses for its own scripts
+    fn chunk_name(&self, node: &DependencyNode) -> String {
+        format!("@{}/{}", self.resource_name, self.relative_path(&node.path))
+    }
+
+    fn get_source(&self, name: &str, node: &DependencyNode) -> String {
+        let contents = node.get_contents();
+        if !self.minify {
+            return contents.to_string();
+        }
+
+        match minifier::minify(contents, self.rename_locals, self.lua_version) {
+            Ok(minified) => {
+                logger::log_info(
+                    format!(
+                        "{} Minified {}: {} -> {} bytes",
+                        &self.log_prefix,
+                        name,
+                        contents.len(),
+                        minified.len()
+                    )
+                    .as_str(),
+                );
+                minified
+            }
+            Err(error) => {
+                logger::log_warn(
+                    format!(
+                        "{} Could not minify {}:{}:{}: {}",
+                        &self.log_prefix,
+                        node.path.display(),
+                        error.line,
+                        error.column,
+                        error.message
+                    )
+                    .as_str(),
+                );
+                contents.to_string()
+            }
+        }
+    }
+
+    // in debug mode every chunk is loaded from its original source, so runtime errors point to the
+    // original file and line instead of the bundle
+    fn load_chunk(&self, name: &str, node: &DependencyNode) -> String {
+        format!(
+            "assert({})",
+            self.load_call(
+                &to_long_string(&self.get_source(name, node)),
+                &to_lua_string(&self.chunk_name(node))
+            )
+        )
+    }
+
+    fn load_call(&self, source: &str, chunk_name: &str) -> String {
+        match self.require_mode {
+            RequireMode::Global => format!("load({}, {})", source, chunk_name),
+            RequireMode::Local => {
+                format!("load({}, {}, \"t\", ____bundle__env)", source, chunk_name)
+            }
+        }
+    }
+
+    fn render_runtime(&self) -> String {
+        let mut runtime = BUNDLE_BOILERPLATE.to_string();
+        runtime.push_str(match self.require_mode {
+            RequireMode::Global => GLOBAL_REQUIRE,
+            RequireMode::Local => LOCAL_REQUIRE,
+        });
+        if self.runtime == RuntimeMode::Dev {
+            runtime.push_str(&DEV_RUNTIME.replace(
+                "{load}",
+                &self.load_call("source", "\"@\" .. resource .. \"/\" .. path"),
+            ));
+        }
+        runtime
+    }
+
+    fn render_module(&self, name: &str) -> String {
+        let module = &self.modules[name];
+        let mut output = match self.debug {
+            true => format!(
+                "____bundle__dict[{}] = {}\n",
+                to_lua_string(name),
+                self.load_chunk(name, module)
+            ),
+            false => format!(
+                "____bundle__dict[{}] = function()\n{}\nend\n",
+                to_lua_string(name),
+                self.get_source(name, module)
+            ),
+        };
+        if self.runtime == RuntimeMode::Dev {
+            output.push_str(&format!(
+                "____bundle__paths[{}] = {}\n",
+                to_lua_string(name),
+                to_lua_string(&self.relative_path(&module.path))
+            ));
+        }
+        output
+    }
+
+    // in eager mode modules are initialised as soon as the chunk loads, dependencies first
+    fn render_eager_init(&self, modules: &[&String], require: &str) -> String {
+        if self.runtime != RuntimeMode::Eager || modules.is_empty() {
+            return String::new();
+        }
+        format!(
+            "for _, module in ipairs({{ {} }}) do\n    {}(module)\nend\n",
+            modules
+                .iter()
+                .map(|name| to_lua_string(name))
+                .collect::<Vec<String>>()
+                .join(", "),
+            require
+        )
+    }
+
+    // an entrypoint that shares the chunk with others is wrapped in a function, so its locals and a
+    // top level `return` don't affect the next one. In debug mode every chunk is loaded on its own
+    fn render_entrypoint(&self, entrypoint: &Entrypoint, is_shared: bool) -> String {
+        if self.debug {
+            return format!(
+                "{}(...)\n",
+                self.load_chunk(&entrypoint.name, &entrypoint.node)
             );
-            out_file.write_all(content.as_bytes()).unwrap();
         }
-        out_file
-            .write_all(&self.main_node.as_ref().unwrap().get_contents().as_bytes())
-            .unwrap();
+        let source = self.get_source(&entrypoint.name, &entrypoint.node);
+        // the source can end with a comment that would swallow what comes after it
+        let source = source.strip_suffix('\n').unwrap_or(&source);
+        match is_shared {
+            true => SHARED_ENTRYPOINT.replace("{source}", source),
+            false => format!("{}\n", source),
+        }
+    }
+
+    // returns the contents of every output file. Modules only reachable from an entrypoint with
+    // its own output go into that file, everything else goes into the shared chunk at `output`
+    pub fn render(&self) -> Vec<(PathBuf, String)> {
+        let all_requires: Vec<String> = self
+            .entrypoints
+            .iter()
+            .flat_map(|entrypoint| entrypoint.node.requires.clone())
+            .collect();
+        let module_order = self.collect_modules(&all_requires, &mut HashSet::new());
+        let reachable: Vec<HashSet<String>> = self
+            .entrypoints
+            .iter()
+            .map(|entrypoint| {
+                self.collect_modules(&entrypoint.node.requires, &mut HashSet::new())
+                    .into_iter()
+                    .collect()
+            })
+            .collect();
+        let owner = |name: &String| {
+            let mut owners = (0..self.entrypoints.len()).filter(|i| reachable[*i].contains(name));
+            match (owners.next(), owners.next()) {
+                (Some(i), None) if self.entrypoints[i].output.is_some() => Some(i),
+                _ => None,
+            }
+        };
+
+        let mut shared_chunk = self.header.clone();
+        if let Some(build_info) = &self.build_info {
+            let fields: Vec<String> = build_info
+                .fields()
+                .into_iter()
+                .filter_map(|(name, value)| {
+                    value.map(|value| format!("{} = {}", name, to_lua_string(value)))
+                })
+                .collect();
+            shared_chunk.push_str(&format!("__JADE_BUILD = {{ {} }}\n", fields.join(", ")));
+        }
+        shared_chunk.push_str(&self.render_runtime());
+        let is_split = self
+            .entrypoints
+            .iter()
+            .any(|entrypoint| entrypoint.output.is_some());
+        if is_split {
+            let mut fields = vec!["modules = ____bundle__dict", "require = __jade_require"];
+            if self.require_mode == RequireMode::Local {
+                fields.push("env = ____bundle__env");
+            }
+            if self.runtime == RuntimeMode::Dev {
+                fields.push("paths = ____bundle__paths");
+            }
+            shared_chunk.push_str(
+                format!(
+                    "rawset(_G, {}, {{ {} }})\n",
+                    to_lua_string(&self.registry_name),
+                    fields.join(", ")
+                )
+                .as_str(),
+            );
+        }
+        let shared_modules: Vec<&String> = module_order
+            .iter()
+            .filter(|name| owner(name).is_none())
+            .collect();
+        for name in &shared_modules {
+            shared_chunk.push_str(&self.render_module(name));
+        }
+        shared_chunk.push_str(&self.render_eager_init(&shared_modules, "__jade_require"));
+        let shared_entrypoints: Vec<&Entrypoint> = self
+            .entrypoints
+            .iter()
+            .filter(|entrypoint| entrypoint.output.is_none())
+            .collect();
+        for entrypoint in &shared_entrypoints {
+            shared_chunk
+                .push_str(&self.render_entrypoint(entrypoint, shared_entrypoints.len() > 1));
+        }
+
+        let mut outputs = vec![(self.output.clone(), shared_chunk)];
+        for (i, entrypoint) in self.entrypoints.iter().enumerate() {
+            let Some(output) = &entrypoint.output else {
+                continue;
+            };
+            let mut chunk = format!(
+                "{}local ____bundle = assert(rawget(_G, {}), \"{} must be loaded first\")\n",
+                &self.header,
+                to_lua_string(&self.registry_name),
+                self.relative_path(&self.output)
+            );
+            chunk.push_str("local ____bundle__dict = ____bundle.modules\n");
+            if self.require_mode == RequireMode::Local {
+                chunk.push_str(
+                    "local require, ____bundle__env = ____bundle.require, ____bundle.env\n",
+                );
+            }
+            if self.runtime == RuntimeMode::Dev {
+                chunk.push_str("local ____bundle__paths = ____bundle.paths\n");
+            }
+            let own_modules: Vec<&String> = module_order
+                .iter()
+                .filter(|name| owner(name) == Some(i))
+                .collect();
+            for name in &own_modules {
+                chunk.push_str(&self.render_module(name));
+            }
+            chunk.push_str(&self.render_eager_init(&own_modules, "____bundle.require"));
+            chunk.push_str(&self.render_entrypoint(entrypoint, false));
+            outputs.push((output.clone(), chunk));
+        }
+        outputs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::build_step::BuildStep;
+    use crate::bundler::bundle_step::BundleEntrypoint;
+    use std::fs;
+
+    fn write_module(dir: &std::path::Path, name: &str, contents: &str) {
+        let path = dir.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    // the literal requires found in `source`, in order
+    fn required_modules(source: &str) -> Vec<String> {
+        find_require_calls(&lexer::tokenize(source).unwrap())
+            .into_iter()
+            .filter_map(|call| match call {
+                RequireCall::Literal { module_name, .. } => Some(module_name),
+                RequireCall::Dynamic { .. } => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn finds_every_literal_require_form() {
+        let source = "require \"a\"\nrequire 'b'\nrequire [[c]]\nrequire [==[d]==]\n\
+                      require(\"e\")\nlocal f = require ( 'f' )";
+        assert_eq!(required_modules(source), vec!["a", "b", "c", "d", "e", "f"]);
+    }
+
+    #[test]
+    fn skips_requires_that_are_not_global_calls() {
+        let source = "obj.require(\"a\") obj:require(\"b\") obj?.require(\"c\")\n\
+                      local function require(name) end\nlocal require = 1";
+        assert!(required_modules(source).is_empty());
+    }
+
+    #[test]
+    fn skips_requires_in_comments_and_strings() {
+        let source = "-- require(\"a\")\n--[[ require \"b\" ]]\n--[==[\nrequire 'c'\n]==]\n\
+                      local s = \"require('d')\" .. [[require \"e\"]]\nrequire(\"f\")";
+        assert_eq!(required_modules(source), vec!["f"]);
+    }
+
+    #[test]
+    fn warns_about_dynamic_requires_with_their_position() {
+        let mut node = DependencyNode {
+            path: PathBuf::from("main.lua"),
+            contents: "local name = \"a\"\nlocal m =   require(name)\nrequire { \"b\" }\n"
+                .to_string(),
+            requires: Vec::new(),
+        };
+        let defines = HashMap::new();
+        let mut state = ScanState {
+            defines: &defines,
+            source_dirs: &[],
+            search_path: &[],
+            externals: &[],
+            found_externals: Vec::new(),
+            errors: Vec::new(),
+            warnings: Vec::new(),
+            stack: Vec::new(),
+            cycles: Vec::new(),
+        };
+        node.scan_dependencies("main", &mut HashMap::new(), &mut state);
+
+        assert!(state.errors.is_empty());
+        assert!(node.requires.is_empty());
+        assert_eq!(
+            state.warnings,
+            vec![
+                "Dynamic require at main.lua:2:13 can not be bundled",
+                "Dynamic require at main.lua:3:1 can not be bundled",
+            ]
+        );
+    }
+
+    // a bundle of the given entrypoints in `dir`/src, none of them with an output of its own
+    fn bundle_step(dir: &Path, output: &str, entrypoints: &[&str]) -> BundleStep {
+        let source_dir = dir.join("src");
+        BundleStep {
+            name: "test".to_string(),
+            output: dir.join(output),
+            source_dirs: vec![source_dir.clone()],
+            search_path: vec!["?.lua".to_string(), "?/init.lua".to_string()],
+            entrypoints: entrypoints
+                .iter()
+                .map(|entrypoint| BundleEntrypoint {
+                    path: source_dir.join(entrypoint),
+                    output: None,
+                })
+                .collect(),
+            circular_dependencies: CircularDependencyMode::Error,
+            debug: false,
+            minify: false,
+            rename_locals: false,
+            lua_version: LuaVersion::Lua54,
+            report_unused: false,
+            runtime: RuntimeMode::Lazy,
+            require_mode: RequireMode::Global,
+            bytecode: false,
+            strip_debug_info: false,
+            banner: None,
+            build_info: None,
+            defines: HashMap::new(),
+            externals: Vec::new(),
+            resource_path: dir.to_path_buf(),
+        }
+    }
+
+    #[test]
+    fn entrypoints_in_the_shared_chunk_have_their_own_scope() {
+        let dir = std::env::temp_dir().join(format!("jade_entrypoints_{}", std::process::id()));
+        let source_dir = dir.join("src");
+        // no final newline, so the comment would run into the next entrypoint
+        write_module(
+            &source_dir,
+            "one.lua",
+            "local x = 1\nprint(\"one\", x)\nreturn x -- done",
+        );
+        write_module(
+            &source_dir,
+            "two.lua",
+            "local y = x or 2\nprint(\"two\", y)\n",
+        );
+
+        let step = bundle_step(&dir, "out.lua", &["one.lua", "two.lua"]);
+        let outputs = Bundler::new(&step, "test").unwrap().render();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(outputs.len(), 1);
+
+        let lua = mlua::Lua::new();
+        lua.load(
+            "printed = {} print = function(...) printed[#printed + 1] = table.concat({...}, \" \") end",
+        )
+        .exec()
+        .unwrap();
+        lua.load(&outputs[0].1).exec().unwrap();
+        let printed: Vec<String> = lua.load("printed").eval().unwrap();
+        assert_eq!(printed, vec!["one 1", "two 2"]);
+    }
+
+    #[test]
+    fn bundle_output_is_deterministic() {
+        let dir = std::env::temp_dir().join(format!("jade_deterministic_{}", std::process::id()));
+        let source_dir = dir.join("src");
+        write_module(
+            &source_dir,
+            "main.lua",
+            "local a = require(\"a\")\nlocal b = require(\"b\")\nlocal c = require(\"c\")\n",
+        );
+        write_module(&source_dir, "a.lua", "return require(\"shared.util\")\n");
+        write_module(&source_dir, "b.lua", "return require(\"shared.util\")\n");
+        write_module(
+            &source_dir,
+            "c.lua",
+            "return { require(\"d\"), require(\"e\") }\n",
+        );
+        write_module(&source_dir, "d.lua", "return 4\n");
+        write_module(&source_dir, "e.lua", "return 5\n");
+        write_module(&source_dir, "shared/util.lua", "return {}\n");
+
+        let build = |output: &str| {
+            let step = bundle_step(&dir, output, &["main.lua"]);
+            step.build(&"test".to_string()).unwrap();
+            fs::read(&step.output).unwrap()
+        };
+
+        let first = build("first.lua");
+        let second = build("second.lua");
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(first, second);
     }
 }
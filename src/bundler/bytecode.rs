@@ -0,0 +1,13 @@
+use mlua::Lua;
+
+// compiles a chunk to lua 5.4 bytecode with the embedded compiler, `chunk_name` is what error
+// messages and tracebacks show as the source of the chunk
+pub fn compile(source: &str, chunk_name: &str, strip_debug_info: bool) -> Result<Vec<u8>, String> {
+    let lua = Lua::new();
+    let function = lua
+        .load(source)
+        .set_name(chunk_name)
+        .into_function()
+        .map_err(|error| error.to_string())?;
+    Ok(function.dump(strip_debug_info))
+}
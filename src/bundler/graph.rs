@@ -0,0 +1,101 @@
+use super::literals::to_lua_string;
+use json::{object, JsonValue};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GraphFormat {
+    Dot,
+    Json,
+}
+
+#[derive(Debug)]
+pub struct GraphNode {
+    pub name: String,
+    // relative to the resource folder
+    pub path: String,
+    // size in bytes of the code that ends up in the bundle
+    pub size: usize,
+}
+
+// the require graph of one bundle, edges go from the requiring module to the required one
+#[derive(Debug)]
+pub struct ModuleGraph {
+    pub bundle: String,
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<(String, String)>,
+}
+
+// dot reads quotes, backslashes and newlines escaped the way lua strings escape them
+fn quote(text: &str) -> String {
+    to_lua_string(text)
+}
+
+fn to_dot(resource_name: &str, graphs: &[ModuleGraph]) -> String {
+    let mut output = format!("digraph {} {{\n", quote(resource_name));
+    output.push_str("    node [shape=box];\n");
+    for graph in graphs {
+        // node ids are prefixed with the bundle so that bundles sharing a module stay apart
+        let id = |name: &str| quote(&format!("{}:{}", graph.bundle, name));
+        output.push_str(&format!(
+            "    subgraph {} {{\n        label={};\n",
+            quote(&format!("cluster_{}", graph.bundle)),
+            quote(&graph.bundle)
+        ));
+        for node in &graph.nodes {
+            output.push_str(&format!(
+                "        {} [label={}];\n",
+                id(&node.name),
+                quote(&format!(
+                    "{}\n{} ({} bytes)",
+                    node.name, node.path, node.size
+                ))
+            ));
+        }
+        for (from, to) in &graph.edges {
+            output.push_str(&format!("        {} -> {};\n", id(from), id(to)));
+        }
+        output.push_str("    }\n");
+    }
+    output.push_str("}\n");
+    output
+}
+
+fn to_json(resource_name: &str, graphs: &[ModuleGraph]) -> String {
+    let bundles: Vec<JsonValue> = graphs
+        .iter()
+        .map(|graph| {
+            object! {
+                name: graph.bundle.as_str(),
+                nodes: graph
+                    .nodes
+                    .iter()
+                    .map(|node| object! {
+                        name: node.name.as_str(),
+                        path: node.path.as_str(),
+                        size: node.size,
+                    })
+                    .collect::<Vec<JsonValue>>(),
+                edges: graph
+                    .edges
+                    .iter()
+                    .map(|(from, to)| object! { from: from.as_str(), to: to.as_str() })
+                    .collect::<Vec<JsonValue>>(),
+            }
+        })
+        .collect();
+    let mut output = json::stringify_pretty(
+        object! {
+            resource: resource_name,
+            bundles: bundles,
+        },
+        2,
+    );
+    output.push('\n');
+    output
+}
+
+pub fn render(resource_name: &str, graphs: &[ModuleGraph], format: GraphFormat) -> String {
+    match format {
+        GraphFormat::Dot => to_dot(resource_name, graphs),
+        GraphFormat::Json => to_json(resource_name, graphs),
+    }
+}
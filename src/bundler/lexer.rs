@@ -0,0 +1,401 @@
+pub const KEYWORDS: [&str; 22] = [
+    "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "goto", "if", "in",
+    "local", "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+];
+
+// longest symbols first so that the greedy match picks "..." over ".." over "."
+const SYMBOLS: [&str; 44] = [
+    "...", "<<=", ">>=", "<<", ">>", "//", "==", "~=", "<=", ">=", "::", "..", "+=", "-=", "*=",
+    "/=", "&=", "|=", "^=", "?.", "?[", "+", "-", "*", "/", "%", "^", "#", "&", "~", "|", "<", ">",
+    "=", "(", ")", "{", "}", "[", "]", ";", ":", ",", ".",
+];
+
+// the compound assignments of CfxLua, `a += 1` is `a = a + 1`
+pub const COMPOUND_ASSIGNMENTS: [&str; 9] =
+    ["+=", "-=", "*=", "/=", "<<=", ">>=", "&=", "|=", "^="];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    Name,
+    Keyword,
+    // holds the decoded value of the literal, the raw source is kept in `Token::text`
+    String(String),
+    Number,
+    // a CfxLua `backtick` string, replaced by its joaat hash when the chunk is compiled
+    Hash,
+    Symbol,
+    Comment,
+    Eof,
+}
+
+#[derive(Debug, Clone)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub text: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug)]
+pub struct LexError {
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+struct Lexer {
+    chars: Vec<char>,
+    position: usize,
+    line: usize,
+    column: usize,
+}
+
+// accepts the numerals lua does: decimal or hexadecimal, with an optional fraction and exponent
+fn is_valid_number(text: &str) -> bool {
+    let lower = text.to_ascii_lowercase();
+    let (digits, exponent_mark, is_digit): (&str, char, fn(&char) -> bool) =
+        match lower.strip_prefix("0x") {
+            Some(digits) => (digits, 'p', char::is_ascii_hexdigit),
+            None => (lower.as_str(), 'e', char::is_ascii_digit),
+        };
+    let (mantissa, exponent) = match digits.split_once(exponent_mark) {
+        Some((mantissa, exponent)) => (mantissa, Some(exponent)),
+        None => (digits, None),
+    };
+    let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let exponent_is_valid = exponent.is_none_or(|exponent| {
+        let exponent = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+        !exponent.is_empty() && exponent.chars().all(|c| c.is_ascii_digit())
+    });
+    (!integer.is_empty() || !fraction.is_empty())
+        && integer.chars().all(|c| is_digit(&c))
+        && fraction.chars().all(|c| is_digit(&c))
+        && exponent_is_valid
+}
+
+impl Token {
+    pub fn is_symbol(&self, symbol: &str) -> bool {
+        self.kind == TokenKind::Symbol && self.text == symbol
+    }
+
+    pub fn is_keyword(&self, keyword: &str) -> bool {
+        self.kind == TokenKind::Keyword && self.text == keyword
+    }
+}
+
+impl Lexer {
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.position + offset).copied()
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let c = self.peek(0)?;
+        self.position += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn starts_with(&self, text: &str) -> bool {
+        text.chars()
+            .enumerate()
+            .all(|(i, c)| self.peek(i) == Some(c))
+    }
+
+    fn error(&self, message: &str, line: usize, column: usize) -> LexError {
+        LexError {
+            message: message.to_string(),
+            line,
+            column,
+        }
+    }
+
+    // returns the level of a long bracket ("[[" is 0, "[==[" is 2) if one starts at the cursor
+    fn long_bracket_level(&self) -> Option<usize> {
+        if self.peek(0) != Some('[') {
+            return None;
+        }
+        let mut level = 0;
+        while self.peek(level + 1) == Some('=') {
+            level += 1;
+        }
+        match self.peek(level + 1) {
+            Some('[') => Some(level),
+            _ => None,
+        }
+    }
+
+    fn read_long_bracket(&mut self, level: usize) -> Result<String, LexError> {
+        let (line, column) = (self.line, self.column);
+        for _ in 0..level + 2 {
+            self.advance();
+        }
+        // a newline right after the opening bracket is not part of the string
+        if self.peek(0) == Some('\r') {
+            self.advance();
+        }
+        if self.peek(0) == Some('\n') {
+            self.advance();
+        }
+
+        let closing = format!("]{}]", "=".repeat(level));
+        let mut value = String::new();
+        loop {
+            if self.starts_with(&closing) {
+                for _ in 0..closing.len() {
+                    self.advance();
+                }
+                return Ok(value);
+            }
+            match self.advance() {
+                Some(c) => value.push(c),
+                None => return Err(self.error("unfinished long string or comment", line, column)),
+            }
+        }
+    }
+
+    fn read_hex_digits(&mut self, count: usize) -> Option<u32> {
+        let mut value = 0;
+        for _ in 0..count {
+            value = value * 16 + self.peek(0)?.to_digit(16)?;
+            self.advance();
+        }
+        Some(value)
+    }
+
+    fn read_escape(&mut self, value: &mut String) -> Result<(), LexError> {
+        let (line, column) = (self.line, self.column);
+        let invalid = |lexer: &Lexer| lexer.error("invalid escape sequence", line, column);
+        match self.advance() {
+            Some('n') => value.push('\n'),
+            Some('t') => value.push('\t'),
+            Some('r') => value.push('\r'),
+            Some('a') => value.push('\u{07}'),
+            Some('b') => value.push('\u{08}'),
+            Some('f') => value.push('\u{0c}'),
+            Some('v') => value.push('\u{0b}'),
+            Some('\n') => value.push('\n'),
+            Some(c @ ('\\' | '"' | '\'')) => value.push(c),
+            Some('z') => {
+                while self.peek(0).is_some_and(|c| c.is_whitespace()) {
+                    self.advance();
+                }
+            }
+            Some('x') => {
+                let code = self.read_hex_digits(2).ok_or_else(|| invalid(self))?;
+                value.push(char::from_u32(code).ok_or_else(|| invalid(self))?);
+            }
+            Some('u') => {
+                if self.advance() != Some('{') {
+                    return Err(invalid(self));
+                }
+                let mut code: u32 = 0;
+                while let Some(digit) = self.peek(0).and_then(|c| c.to_digit(16)) {
+                    code = code.checked_mul(16).ok_or_else(|| invalid(self))? + digit;
+                    self.advance();
+                }
+                if self.advance() != Some('}') {
+                    return Err(invalid(self));
+                }
+                value.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+            }
+            Some(c) if c.is_ascii_digit() => {
+                let mut code = c.to_digit(10).unwrap();
+                for _ in 0..2 {
+                    match self.peek(0).and_then(|c| c.to_digit(10)) {
+                        Some(digit) => {
+                            code = code * 10 + digit;
+                            self.advance();
+                        }
+                        None => break,
+                    }
+                }
+                value.push(char::from_u32(code).ok_or_else(|| invalid(self))?);
+            }
+            _ => return Err(invalid(self)),
+        }
+        Ok(())
+    }
+
+    fn read_string(&mut self, quote: char) -> Result<String, LexError> {
+        let (line, column) = (self.line, self.column);
+        self.advance();
+        let mut value = String::new();
+        loop {
+            match self.peek(0) {
+                None | Some('\n') => {
+                    return Err(self.error("unfinished string", line, column));
+                }
+                Some('\\') => {
+                    self.advance();
+                    self.read_escape(&mut value)?;
+                }
+                Some(c) => {
+                    self.advance();
+                    if c == quote {
+                        return Ok(value);
+                    }
+                    value.push(c);
+                }
+            }
+        }
+    }
+
+    fn read_hash(&mut self) -> Result<(), LexError> {
+        let (line, column) = (self.line, self.column);
+        self.advance();
+        loop {
+            match self.advance() {
+                None | Some('\n') => {
+                    return Err(self.error("unfinished hash string", line, column))
+                }
+                Some('`') => return Ok(()),
+                Some(_) => {}
+            }
+        }
+    }
+
+    fn read_number(&mut self) {
+        let is_hex = self.peek(0) == Some('0') && matches!(self.peek(1), Some('x' | 'X'));
+        let exponents = if is_hex { ['p', 'P'] } else { ['e', 'E'] };
+        while let Some(c) = self.peek(0) {
+            if exponents.contains(&c) && matches!(self.peek(1), Some('+' | '-')) {
+                self.advance();
+                self.advance();
+            } else if c.is_ascii_alphanumeric() || c == '.' || c == '_' {
+                self.advance();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn next_token(&mut self) -> Result<Token, LexError> {
+        while self.peek(0).is_some_and(|c| c.is_whitespace()) {
+            self.advance();
+        }
+
+        let (line, column, start) = (self.line, self.column, self.position);
+        let kind = match self.peek(0) {
+            None => TokenKind::Eof,
+            Some('-') if self.peek(1) == Some('-') => {
+                self.advance();
+                self.advance();
+                match self.long_bracket_level() {
+                    Some(level) => {
+                        self.read_long_bracket(level)?;
+                    }
+                    None => {
+                        while self.peek(0).is_some_and(|c| c != '\n') {
+                            self.advance();
+                        }
+                    }
+                }
+                TokenKind::Comment
+            }
+            // a CfxLua comment, the parser only accepts it in the cfx dialect
+            Some('/') if self.peek(1) == Some('*') => {
+                self.advance();
+                self.advance();
+                while !self.starts_with("*/") {
+                    if self.advance().is_none() {
+                        return Err(self.error("unfinished comment", line, column));
+                    }
+                }
+                self.advance();
+                self.advance();
+                TokenKind::Comment
+            }
+            Some(quote @ ('"' | '\'')) => TokenKind::String(self.read_string(quote)?),
+            Some('`') => {
+                self.read_hash()?;
+                TokenKind::Hash
+            }
+            Some('[') if self.long_bracket_level().is_some() => {
+                let level = self.long_bracket_level().unwrap();
+                TokenKind::String(self.read_long_bracket(level)?)
+            }
+            Some(c)
+                if c.is_ascii_digit()
+                    || (c == '.' && self.peek(1).is_some_and(|c| c.is_ascii_digit())) =>
+            {
+                self.read_number();
+                let text: String = self.chars[start..self.position].iter().collect();
+                if !is_valid_number(&text) {
+                    return Err(self.error(
+                        format!("malformed number near '{}'", text).as_str(),
+                        line,
+                        column,
+                    ));
+                }
+                TokenKind::Number
+            }
+            Some(c) if c.is_alphabetic() || c == '_' => {
+                while self
+                    .peek(0)
+                    .is_some_and(|c| c.is_alphanumeric() || c == '_')
+                {
+                    self.advance();
+                }
+                let word: String = self.chars[start..self.position].iter().collect();
+                if KEYWORDS.contains(&word.as_str()) {
+                    TokenKind::Keyword
+                } else {
+                    TokenKind::Name
+                }
+            }
+            Some(c) => match SYMBOLS.iter().find(|symbol| self.starts_with(symbol)) {
+                Some(symbol) => {
+                    for _ in 0..symbol.len() {
+                        self.advance();
+                    }
+                    TokenKind::Symbol
+                }
+                None => {
+                    return Err(self.error(
+                        format!("unexpected symbol '{}'", c).as_str(),
+                        line,
+                        column,
+                    ))
+                }
+            },
+        };
+
+        Ok(Token {
+            kind,
+            text: self.chars[start..self.position].iter().collect(),
+            line,
+            column,
+        })
+    }
+}
+
+pub fn tokenize(source: &str) -> Result<Vec<Token>, LexError> {
+    let mut lexer = Lexer {
+        chars: source.chars().collect(),
+        position: 0,
+        line: 1,
+        column: 1,
+    };
+
+    // a shebang line is skipped by the lua loader, so it is skipped here too
+    if lexer.starts_with("#") {
+        while lexer.peek(0).is_some_and(|c| c != '\n') {
+            lexer.advance();
+        }
+    }
+
+    let mut tokens = Vec::new();
+    loop {
+        let token = lexer.next_token()?;
+        let is_eof = token.kind == TokenKind::Eof;
+        tokens.push(token);
+        if is_eof {
+            return Ok(tokens);
+        }
+    }
+}
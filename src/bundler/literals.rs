@@ -0,0 +1,70 @@
+use super::lexer::KEYWORDS;
+use json::JsonValue;
+
+// quotes the text as a lua string literal
+pub fn to_lua_string(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            c if c.is_ascii_control() => quoted.push_str(format!("\\{:03}", c as u8).as_str()),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// wraps the text in a lua long string using a bracket level that does not appear in it, the
+// leading newline is dropped by lua so the line numbers of the text are kept intact
+pub fn to_long_string(text: &str) -> String {
+    let mut level = 0;
+    // a trailing "]=" would merge with the closing bracket too
+    while text.contains(format!("]{}]", "=".repeat(level)).as_str())
+        || text.ends_with(format!("]{}", "=".repeat(level)).as_str())
+    {
+        level += 1;
+    }
+    let equals = "=".repeat(level);
+    format!("[{}[\n{}]{}]", equals, text, equals)
+}
+
+fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !KEYWORDS.contains(&text)
+}
+
+pub fn json_to_lua(value: &JsonValue) -> String {
+    match value {
+        JsonValue::Null => "nil".to_string(),
+        JsonValue::Boolean(value) => value.to_string(),
+        JsonValue::Number(number) => number.to_string(),
+        JsonValue::Short(_) | JsonValue::String(_) => to_lua_string(value.as_str().unwrap()),
+        JsonValue::Array(values) => format!(
+            "{{{}}}",
+            values
+                .iter()
+                .map(json_to_lua)
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+        JsonValue::Object(object) => format!(
+            "{{{}}}",
+            object
+                .iter()
+                .map(|(key, value)| match is_identifier(key) {
+                    true => format!("{} = {}", key, json_to_lua(value)),
+                    false => format!("[{}] = {}", to_lua_string(key), json_to_lua(value)),
+                })
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+    }
+}
@@ -0,0 +1,224 @@
+use super::lexer::{self, Token, TokenKind, KEYWORDS};
+use super::parser::{self, LuaVersion, ParseError, ScopeInfo};
+use std::collections::HashSet;
+
+const NAME_START_CHARS: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ_";
+const NAME_CHARS: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ_0123456789";
+
+// the n-th shortest identifier: a, b, ..., _, aa, ba, ...
+fn generate_name(mut index: usize) -> String {
+    let start_chars: Vec<char> = NAME_START_CHARS.chars().collect();
+    let chars: Vec<char> = NAME_CHARS.chars().collect();
+
+    let mut name = String::new();
+    name.push(start_chars[index % start_chars.len()]);
+    index /= start_chars.len();
+    while index > 0 {
+        index -= 1;
+        name.push(chars[index % chars.len()]);
+        index /= chars.len();
+    }
+    name
+}
+
+// assigns the shortest possible name to every local, two locals only share a name when their
+// scopes do not overlap, and no local is ever renamed to a global referenced by the chunk
+fn rename_locals(info: &ScopeInfo) -> Vec<Option<String>> {
+    let mut reserved: HashSet<&str> = KEYWORDS.into_iter().collect();
+    reserved.insert("_ENV");
+    reserved.extend(info.globals.iter().map(|name| name.as_str()));
+    // implicit locals (`self`) have no token that could be renamed
+    reserved.extend(
+        info.bindings
+            .iter()
+            .filter(|binding| binding.declaration.is_none() || binding.keeps_name)
+            .map(|binding| binding.name.as_str()),
+    );
+
+    let mut order: Vec<usize> = (0..info.bindings.len())
+        .filter(|binding| {
+            info.bindings[*binding].declaration.is_some() && !info.bindings[*binding].keeps_name
+        })
+        .collect();
+    order.sort_by_key(|binding| info.bindings[*binding].declaration);
+
+    let mut new_names = vec![None; info.bindings.len()];
+    let mut active: Vec<(usize, String)> = Vec::new();
+    for binding in order {
+        let start = info.bindings[binding].declaration.unwrap();
+        active.retain(|(scope_end, _)| *scope_end >= start);
+        if info.bindings[binding].name == "_ENV" {
+            continue;
+        }
+
+        let name = (0..)
+            .map(generate_name)
+            .find(|name| {
+                !reserved.contains(name.as_str())
+                    && !active.iter().any(|(_, active_name)| active_name == name)
+            })
+            .unwrap();
+        active.push((info.bindings[binding].scope_end, name.clone()));
+        new_names[binding] = Some(name);
+    }
+    new_names
+}
+
+// two tokens can be written next to each other as long as the lexer still reads them back as the
+// same two tokens, e.g. `a b`, `- -` and `1 ..` need a space between them. The leading space keeps
+// a `#` from being read as a shebang line
+fn needs_space(previous: &str, next: &str) -> bool {
+    match lexer::tokenize(format!(" {}{}", previous, next).as_str()) {
+        Ok(tokens) => tokens.len() != 3 || tokens[0].text != previous || tokens[1].text != next,
+        Err(_) => true,
+    }
+}
+
+// strips comments and whitespace, and optionally renames locals, without changing behaviour
+pub fn minify(source: &str, rename: bool, version: LuaVersion) -> Result<String, ParseError> {
+    let tokens: Vec<Token> = lexer::tokenize(source)?
+        .into_iter()
+        .filter(|token| token.kind != TokenKind::Comment)
+        .collect();
+    let info = parser::parse(&tokens, version)?;
+    let new_names = match rename {
+        true => rename_locals(&info),
+        false => vec![None; info.bindings.len()],
+    };
+
+    let mut output = String::new();
+    let mut previous: Option<&str> = None;
+    for (i, token) in tokens.iter().enumerate() {
+        if token.kind == TokenKind::Eof {
+            break;
+        }
+        let text = match info.resolutions[i] {
+            Some(binding) => new_names[binding].as_deref().unwrap_or(&token.text),
+            None => &token.text,
+        };
+        if previous.is_some_and(|previous| needs_space(previous, text)) {
+            output.push(' ');
+        }
+        output.push_str(text);
+        previous = Some(text);
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // runs a chunk that returns a string
+    fn run(source: &str) -> String {
+        mlua::Lua::new().load(source).eval::<String>().unwrap()
+    }
+
+    // the minified chunk must return the same value as the original one
+    fn minify_and_compare(source: &str) -> String {
+        let minified = minify(source, true, LuaVersion::Lua54).unwrap();
+        assert_eq!(run(source), run(&minified), "minified to: {}", minified);
+        minified
+    }
+
+    #[test]
+    fn renames_shadowed_locals() {
+        let minified = minify_and_compare(
+            "local value = 1
+            local function add(value) return value + 1 end
+            do
+                local value = value + 10
+                result = value
+            end
+            return add(value) .. ',' .. result .. ',' .. value",
+        );
+        assert!(!minified.contains("value"), "{}", minified);
+        // `result` is a global, so it keeps its name
+        assert!(minified.contains("result"), "{}", minified);
+    }
+
+    #[test]
+    fn local_initializer_refers_to_the_outer_local() {
+        let minified = minify_and_compare(
+            "local x = 'outer'
+            do
+                local x = x .. '!'
+                return x
+            end",
+        );
+        assert!(!minified.contains('x'), "{}", minified);
+    }
+
+    #[test]
+    fn keeps_table_keys_and_field_names() {
+        let minified = minify_and_compare(
+            "local name, value = 'key', 2
+            local t = { name = name, [name] = value, value = value }
+            function t:get() return self.name .. self.value end
+            return t:get() .. t.key .. t['name']",
+        );
+        assert!(minified.contains("{name="), "{}", minified);
+        assert!(minified.contains(",value="), "{}", minified);
+        assert!(minified.contains("self.name"), "{}", minified);
+        assert!(minified.contains(":get()"), "{}", minified);
+    }
+
+    #[test]
+    fn keeps_goto_and_labels() {
+        let minified = minify_and_compare(
+            "local count = 0
+            ::again::
+            count = count + 1
+            if count < 3 then goto again end
+            return tostring(count)",
+        );
+        assert!(minified.contains("::again::"), "{}", minified);
+        assert!(minified.contains("goto again"), "{}", minified);
+    }
+
+    #[test]
+    fn goto_is_a_local_name_in_lua51() {
+        let minified = minify("local goto = 1 return goto", true, LuaVersion::Lua51).unwrap();
+        assert_eq!(minified, "local a=1 return a");
+    }
+
+    #[test]
+    fn keeps_local_attributes() {
+        let minified = minify_and_compare(
+            "local limit <const> = 5
+            local handle <close> = nil
+            return tostring(limit)",
+        );
+        assert!(minified.contains("<const>"), "{}", minified);
+        assert!(minified.contains("<close>"), "{}", minified);
+        assert!(minify("local x <const> = 1", true, LuaVersion::Lua51).is_err());
+    }
+
+    #[test]
+    fn separates_tokens_that_would_merge() {
+        assert!(needs_space("1", ".."));
+        assert!(needs_space("-", "-"));
+        assert!(needs_space("local", "a"));
+        assert!(needs_space(".", ".."));
+        assert!(!needs_space("a", "("));
+        assert!(!needs_space(")", ".."));
+        assert!(!needs_space("\"a\"", ".."));
+
+        let minified = minify_and_compare("return 1 .. 2 .. - -3");
+        assert_eq!(minified, "return 1 ..2 ..- -3");
+    }
+
+    #[test]
+    fn keeps_cfx_hashes_and_compound_assignments() {
+        let source = "local count = 0\ncount += `WEAPON_PISTOL`\ncount -= -1\nreturn count";
+        let minified = minify(source, true, LuaVersion::Cfx).unwrap();
+        assert_eq!(minified, "local a=0 a+=`WEAPON_PISTOL`a-=-1 return a");
+    }
+
+    #[test]
+    fn keeps_the_names_of_unpacked_cfx_locals() {
+        let source = "local count = 1 /* set */ local x, y in { .x, y = count?.z } return x, y";
+        let minified = minify(source, true, LuaVersion::Cfx).unwrap();
+        assert_eq!(minified, "local a=1 local x,y in{.x,y=a?.z}return x,y");
+    }
+}
@@ -1,2 +1,9 @@
 pub mod bundle_step;
 pub mod bundler;
+pub mod bytecode;
+pub mod graph;
+pub mod lexer;
+pub mod literals;
+pub mod minifier;
+pub mod parser;
+pub mod preprocessor;
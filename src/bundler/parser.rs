@@ -0,0 +1,904 @@
+use super::lexer::{self, LexError, Token, TokenKind, COMPOUND_ASSIGNMENTS};
+
+// (left, right) binding power of every binary operator, same values as lparser.c
+const BINARY_PRIORITY: [(&str, u8, u8); 21] = [
+    ("or", 1, 1),
+    ("and", 2, 2),
+    ("<", 3, 3),
+    (">", 3, 3),
+    ("<=", 3, 3),
+    (">=", 3, 3),
+    ("~=", 3, 3),
+    ("==", 3, 3),
+    ("|", 4, 4),
+    ("~", 5, 5),
+    ("&", 6, 6),
+    ("<<", 7, 7),
+    (">>", 7, 7),
+    ("..", 9, 8),
+    ("+", 10, 10),
+    ("-", 10, 10),
+    ("*", 11, 11),
+    ("/", 11, 11),
+    ("//", 11, 11),
+    ("%", 11, 11),
+    ("^", 14, 13),
+];
+const UNARY_PRIORITY: u8 = 12;
+// operators added after lua 5.1
+const LUA54_OPERATORS: [&str; 6] = ["//", "&", "|", "~", "<<", ">>"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LuaVersion {
+    Lua51,
+    Lua54,
+    // lua 5.4 as FiveM runs it, with the syntax extensions of CfxLua
+    Cfx,
+}
+
+#[derive(Debug)]
+pub struct ParseError {
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+// a local variable, `declaration` is the index of the token that declares it (implicit locals like
+// `self` have none) and `scope_end` the index of the token that closes its scope
+#[derive(Debug, Clone)]
+pub struct Binding {
+    pub name: String,
+    pub declaration: Option<usize>,
+    pub scope_end: usize,
+    // the name is read by the program, like the ones of `local a, b in t`, so it can't be renamed
+    pub keeps_name: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct ScopeInfo {
+    pub bindings: Vec<Binding>,
+    // for every token, the binding it declares or references (only set for local variables)
+    pub resolutions: Vec<Option<usize>>,
+    // names that are referenced without a local in scope
+    pub globals: Vec<String>,
+}
+
+impl From<LexError> for ParseError {
+    fn from(error: LexError) -> ParseError {
+        ParseError {
+            message: error.message,
+            line: error.line,
+            column: error.column,
+        }
+    }
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    version: LuaVersion,
+    position: usize,
+    scopes: Vec<Vec<usize>>,
+    info: ScopeInfo,
+}
+
+impl<'a> Parser<'a> {
+    fn current(&self) -> &'a Token {
+        &self.tokens[self.position.min(self.tokens.len() - 1)]
+    }
+
+    fn peek(&self, offset: usize) -> &'a Token {
+        &self.tokens[(self.position + offset).min(self.tokens.len() - 1)]
+    }
+
+    fn next(&mut self) -> &'a Token {
+        let token = self.current();
+        if token.kind != TokenKind::Eof {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn error_near(&self, message: &str) -> ParseError {
+        let token = self.current();
+        let near = match token.kind {
+            TokenKind::Eof => "<eof>".to_string(),
+            _ => format!("'{}'", token.text),
+        };
+        ParseError {
+            message: format!("{} near {}", message, near),
+            line: token.line,
+            column: token.column,
+        }
+    }
+
+    fn check_symbol(&mut self, symbol: &str) -> bool {
+        if self.current().is_symbol(symbol) {
+            self.next();
+            return true;
+        }
+        false
+    }
+
+    fn check_keyword(&mut self, keyword: &str) -> bool {
+        if self.current().is_keyword(keyword) {
+            self.next();
+            return true;
+        }
+        false
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), ParseError> {
+        match self.check_symbol(symbol) {
+            true => Ok(()),
+            false => Err(self.error_near(format!("'{}' expected", symbol).as_str())),
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), ParseError> {
+        match self.check_keyword(keyword) {
+            true => Ok(()),
+            false => Err(self.error_near(format!("'{}' expected", keyword).as_str())),
+        }
+    }
+
+    // like `expect_keyword`, but points back to the token that opened the block
+    fn expect_match(&mut self, keyword: &str, opener: &str, line: usize) -> Result<(), ParseError> {
+        if self.check_keyword(keyword) {
+            return Ok(());
+        }
+        if self.current().line == line {
+            return Err(self.error_near(format!("'{}' expected", keyword).as_str()));
+        }
+        Err(self.error_near(
+            format!(
+                "'{}' expected (to close '{}' at line {})",
+                keyword, opener, line
+            )
+            .as_str(),
+        ))
+    }
+
+    fn expect_name(&mut self) -> Result<usize, ParseError> {
+        if self.current().kind != TokenKind::Name {
+            return Err(self.error_near("<name> expected"));
+        }
+        self.next();
+        Ok(self.position - 1)
+    }
+
+    fn open_scope(&mut self) {
+        self.scopes.push(Vec::new());
+    }
+
+    fn close_scope(&mut self) {
+        let scope_end = self.position;
+        for binding in self.scopes.pop().unwrap() {
+            self.info.bindings[binding].scope_end = scope_end;
+        }
+    }
+
+    // creates a binding that only becomes visible once it is passed to `activate`, so that
+    // `local x = x` still resolves the right hand side to the outer `x`
+    fn declare(&mut self, token: usize) -> usize {
+        self.info.bindings.push(Binding {
+            name: self.tokens[token].text.clone(),
+            declaration: Some(token),
+            scope_end: token,
+            keeps_name: false,
+        });
+        let binding = self.info.bindings.len() - 1;
+        self.info.resolutions[token] = Some(binding);
+        binding
+    }
+
+    fn declare_implicit(&mut self, name: &str) {
+        self.info.bindings.push(Binding {
+            name: name.to_string(),
+            declaration: None,
+            scope_end: self.position,
+            keeps_name: false,
+        });
+        let binding = self.info.bindings.len() - 1;
+        self.activate(binding);
+    }
+
+    fn activate(&mut self, binding: usize) {
+        self.scopes.last_mut().unwrap().push(binding);
+    }
+
+    fn resolve(&mut self, token: usize) {
+        let name = &self.tokens[token].text;
+        let binding = self
+            .scopes
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.iter().rev())
+            .find(|binding| self.info.bindings[**binding].name == *name)
+            .copied();
+        match binding {
+            Some(binding) => self.info.resolutions[token] = Some(binding),
+            None => {
+                if !self.info.globals.contains(name) {
+                    self.info.globals.push(name.clone());
+                }
+            }
+        }
+    }
+
+    fn block_follows(&self) -> bool {
+        let token = self.current();
+        match token.kind {
+            TokenKind::Eof => true,
+            TokenKind::Keyword => {
+                matches!(token.text.as_str(), "else" | "elseif" | "end" | "until")
+            }
+            _ => false,
+        }
+    }
+
+    fn block(&mut self) -> Result<(), ParseError> {
+        while !self.block_follows() {
+            if self.current().is_keyword("return") {
+                self.return_statement()?;
+                break;
+            }
+            self.statement()?;
+        }
+        Ok(())
+    }
+
+    fn scoped_block(&mut self) -> Result<(), ParseError> {
+        self.open_scope();
+        self.block()?;
+        self.close_scope();
+        Ok(())
+    }
+
+    fn return_statement(&mut self) -> Result<(), ParseError> {
+        self.next();
+        if !self.block_follows() && !self.current().is_symbol(";") {
+            self.expression_list()?;
+        }
+        self.check_symbol(";");
+        if !self.block_follows() {
+            return Err(self.error_near("'<eof>' expected"));
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self) -> Result<(), ParseError> {
+        let token = self.current();
+        let line = token.line;
+        if token.is_symbol(";") {
+            self.next();
+            return Ok(());
+        }
+        if token.is_symbol("::") && self.version != LuaVersion::Lua51 {
+            self.next();
+            self.expect_name()?;
+            return self.expect_symbol("::");
+        }
+        if token.kind != TokenKind::Keyword {
+            return self.expression_statement();
+        }
+
+        match token.text.as_str() {
+            "if" => {
+                self.next();
+                self.expression()?;
+                self.expect_keyword("then")?;
+                self.scoped_block()?;
+                while self.current().is_keyword("elseif") {
+                    self.next();
+                    self.expression()?;
+                    self.expect_keyword("then")?;
+                    self.scoped_block()?;
+                }
+                if self.check_keyword("else") {
+                    self.scoped_block()?;
+                }
+                self.expect_match("end", "if", line)
+            }
+            "while" => {
+                self.next();
+                self.expression()?;
+                self.expect_keyword("do")?;
+                self.scoped_block()?;
+                self.expect_match("end", "while", line)
+            }
+            "do" => {
+                self.next();
+                self.scoped_block()?;
+                self.expect_match("end", "do", line)
+            }
+            "for" => self.for_statement(),
+            "repeat" => {
+                self.next();
+                // the condition can see the locals of the loop body
+                self.open_scope();
+                self.block()?;
+                self.expect_match("until", "repeat", line)?;
+                self.expression()?;
+                self.close_scope();
+                Ok(())
+            }
+            "function" => {
+                self.next();
+                let name = self.expect_name()?;
+                self.resolve(name);
+                let mut is_method = false;
+                while self.current().is_symbol(".") || self.current().is_symbol(":") {
+                    is_method = self.next().text == ":";
+                    self.expect_name()?;
+                    if is_method {
+                        break;
+                    }
+                }
+                self.function_body(is_method, line)
+            }
+            "local" => {
+                self.next();
+                if self.check_keyword("function") {
+                    let name = self.expect_name()?;
+                    let binding = self.declare(name);
+                    self.activate(binding);
+                    return self.function_body(false, line);
+                }
+
+                let mut bindings = Vec::new();
+                loop {
+                    let name = self.expect_name()?;
+                    bindings.push(self.declare(name));
+                    if self.version != LuaVersion::Lua51 && self.check_symbol("<") {
+                        self.expect_name()?;
+                        self.expect_symbol(">")?;
+                    }
+                    if !self.check_symbol(",") {
+                        break;
+                    }
+                }
+                // `local a, b in t` reads the fields with the names of the locals
+                if self.version == LuaVersion::Cfx && self.check_keyword("in") {
+                    self.expression()?;
+                    for binding in &bindings {
+                        self.info.bindings[*binding].keeps_name = true;
+                    }
+                } else if self.check_symbol("=") {
+                    self.expression_list()?;
+                }
+                bindings
+                    .into_iter()
+                    .for_each(|binding| self.activate(binding));
+                Ok(())
+            }
+            "return" => Err(self.error_near("'<eof>' expected")),
+            "break" => {
+                self.next();
+                Ok(())
+            }
+            "goto" => {
+                self.next();
+                self.expect_name()?;
+                Ok(())
+            }
+            _ => self.expression_statement(),
+        }
+    }
+
+    fn for_statement(&mut self) -> Result<(), ParseError> {
+        let line = self.next().line;
+        let first = self.expect_name()?;
+        self.open_scope();
+        if self.current().is_symbol("=") {
+            self.next();
+            self.expression()?;
+            self.expect_symbol(",")?;
+            self.expression()?;
+            if self.check_symbol(",") {
+                self.expression()?;
+            }
+            let binding = self.declare(first);
+            self.activate(binding);
+        } else if self.current().is_symbol(",") || self.current().is_keyword("in") {
+            let mut bindings = vec![self.declare(first)];
+            while self.check_symbol(",") {
+                let name = self.expect_name()?;
+                bindings.push(self.declare(name));
+            }
+            self.expect_keyword("in")?;
+            self.expression_list()?;
+            bindings
+                .into_iter()
+                .for_each(|binding| self.activate(binding));
+        } else {
+            return Err(self.error_near("'=' or 'in' expected"));
+        }
+        self.expect_keyword("do")?;
+        self.scoped_block()?;
+        self.close_scope();
+        self.expect_match("end", "for", line)
+    }
+
+    fn is_compound_assignment(&self) -> bool {
+        let token = self.current();
+        self.version == LuaVersion::Cfx
+            && token.kind == TokenKind::Symbol
+            && COMPOUND_ASSIGNMENTS.contains(&token.text.as_str())
+    }
+
+    fn expression_statement(&mut self) -> Result<(), ParseError> {
+        let is_call = self.suffixed_expression()?;
+        // a compound assignment has a single target and value
+        if self.is_compound_assignment() {
+            if is_call {
+                return Err(self.error_near("syntax error"));
+            }
+            self.next();
+            return self.expression();
+        }
+        if self.current().is_symbol("=") || self.current().is_symbol(",") {
+            if is_call {
+                return Err(self.error_near("syntax error"));
+            }
+            while self.check_symbol(",") {
+                if self.suffixed_expression()? {
+                    return Err(self.error_near("syntax error"));
+                }
+            }
+            self.expect_symbol("=")?;
+            self.expression_list()?;
+            return Ok(());
+        }
+        if !is_call {
+            return Err(self.error_near("syntax error"));
+        }
+        Ok(())
+    }
+
+    fn function_body(&mut self, is_method: bool, line: usize) -> Result<(), ParseError> {
+        self.open_scope();
+        if is_method {
+            self.declare_implicit("self");
+        }
+        self.expect_symbol("(")?;
+        if !self.current().is_symbol(")") {
+            loop {
+                if self.check_symbol("...") {
+                    break;
+                }
+                let name = self.expect_name()?;
+                let binding = self.declare(name);
+                self.activate(binding);
+                if !self.check_symbol(",") {
+                    break;
+                }
+            }
+        }
+        self.expect_symbol(")")?;
+        self.block()?;
+        self.close_scope();
+        self.expect_match("end", "function", line)
+    }
+
+    fn expression_list(&mut self) -> Result<(), ParseError> {
+        self.expression()?;
+        while self.check_symbol(",") {
+            self.expression()?;
+        }
+        Ok(())
+    }
+
+    fn expression(&mut self) -> Result<(), ParseError> {
+        self.sub_expression(0)
+    }
+
+    fn supports_operator(&self, operator: &str) -> bool {
+        self.version != LuaVersion::Lua51 || !LUA54_OPERATORS.contains(&operator)
+    }
+
+    fn binary_priority(&self) -> Option<(u8, u8)> {
+        let token = self.current();
+        if token.kind != TokenKind::Symbol && token.kind != TokenKind::Keyword {
+            return None;
+        }
+        if !self.supports_operator(&token.text) {
+            return None;
+        }
+        BINARY_PRIORITY
+            .iter()
+            .find(|(operator, _, _)| *operator == token.text)
+            .map(|(_, left, right)| (*left, *right))
+    }
+
+    fn sub_expression(&mut self, limit: u8) -> Result<(), ParseError> {
+        let token = self.current();
+        if token.is_keyword("not")
+            || token.is_symbol("-")
+            || token.is_symbol("#")
+            || (token.is_symbol("~") && self.supports_operator("~"))
+        {
+            self.next();
+            self.sub_expression(UNARY_PRIORITY)?;
+        } else {
+            self.simple_expression()?;
+        }
+
+        while let Some((left, right)) = self.binary_priority() {
+            if left <= limit {
+                break;
+            }
+            self.next();
+            self.sub_expression(right)?;
+        }
+        Ok(())
+    }
+
+    fn simple_expression(&mut self) -> Result<(), ParseError> {
+        let token = self.current();
+        match &token.kind {
+            TokenKind::Number | TokenKind::String(_) => {
+                self.next();
+                Ok(())
+            }
+            TokenKind::Hash if self.version == LuaVersion::Cfx => {
+                self.next();
+                Ok(())
+            }
+            TokenKind::Keyword if matches!(token.text.as_str(), "nil" | "true" | "false") => {
+                self.next();
+                Ok(())
+            }
+            TokenKind::Keyword if token.text == "function" => {
+                let line = self.next().line;
+                self.function_body(false, line)
+            }
+            TokenKind::Symbol if token.text == "..." => {
+                self.next();
+                Ok(())
+            }
+            TokenKind::Symbol if token.text == "{" => self.table_constructor(),
+            _ => self.suffixed_expression().map(|_| ()),
+        }
+    }
+
+    fn primary_expression(&mut self) -> Result<(), ParseError> {
+        let token = self.current();
+        if token.kind == TokenKind::Name {
+            let name = self.expect_name()?;
+            self.resolve(name);
+            return Ok(());
+        }
+        if token.is_symbol("(") {
+            let line = self.next().line;
+            self.expression()?;
+            if !self.check_symbol(")") {
+                if self.current().line == line {
+                    return Err(self.error_near("')' expected"));
+                }
+                return Err(self
+                    .error_near(format!("')' expected (to close '(' at line {})", line).as_str()));
+            }
+            return Ok(());
+        }
+        Err(self.error_near("unexpected symbol"))
+    }
+
+    // returns whether the expression ends with a function call
+    fn suffixed_expression(&mut self) -> Result<bool, ParseError> {
+        self.primary_expression()?;
+        let mut is_call = false;
+        loop {
+            let token = self.current();
+            match &token.kind {
+                TokenKind::Symbol if token.text == "." => {
+                    self.next();
+                    self.expect_name()?;
+                    is_call = false;
+                }
+                TokenKind::Symbol if token.text == "?." && self.version == LuaVersion::Cfx => {
+                    self.next();
+                    self.expect_name()?;
+                    is_call = false;
+                }
+                TokenKind::Symbol
+                    if token.text == "["
+                        || (token.text == "?[" && self.version == LuaVersion::Cfx) =>
+                {
+                    self.next();
+                    self.expression()?;
+                    self.expect_symbol("]")?;
+                    is_call = false;
+                }
+                TokenKind::Symbol if token.text == ":" => {
+                    self.next();
+                    self.expect_name()?;
+                    self.call_arguments()?;
+                    is_call = true;
+                }
+                TokenKind::Symbol if token.text == "(" || token.text == "{" => {
+                    self.call_arguments()?;
+                    is_call = true;
+                }
+                TokenKind::String(_) => {
+                    self.call_arguments()?;
+                    is_call = true;
+                }
+                _ => return Ok(is_call),
+            }
+        }
+    }
+
+    fn call_arguments(&mut self) -> Result<(), ParseError> {
+        let token = self.current();
+        match &token.kind {
+            TokenKind::String(_) => {
+                self.next();
+                Ok(())
+            }
+            TokenKind::Symbol if token.text == "{" => self.table_constructor(),
+            TokenKind::Symbol if token.text == "(" => {
+                let line = self.next().line;
+                if !self.current().is_symbol(")") {
+                    self.expression_list()?;
+                }
+                if !self.check_symbol(")") {
+                    if self.current().line == line {
+                        return Err(self.error_near("')' expected"));
+                    }
+                    return Err(self.error_near(
+                        format!("')' expected (to close '(' at line {})", line).as_str(),
+                    ));
+                }
+                Ok(())
+            }
+            _ => Err(self.error_near("function arguments expected")),
+        }
+    }
+
+    fn table_constructor(&mut self) -> Result<(), ParseError> {
+        let line = self.next().line;
+        while !self.current().is_symbol("}") {
+            if self.current().kind == TokenKind::Name && self.peek(1).is_symbol("=") {
+                self.next();
+                self.next();
+                self.expression()?;
+            } else if self.version == LuaVersion::Cfx
+                && self.current().is_symbol(".")
+                && self.peek(1).kind == TokenKind::Name
+            {
+                // `{ .a }` is `{ a = true }`
+                self.next();
+                self.next();
+            } else if self.check_symbol("[") {
+                self.expression()?;
+                self.expect_symbol("]")?;
+                self.expect_symbol("=")?;
+                self.expression()?;
+            } else {
+                self.expression()?;
+            }
+            if !self.check_symbol(",") && !self.check_symbol(";") {
+                break;
+            }
+        }
+        if !self.check_symbol("}") {
+            if self.current().line == line {
+                return Err(self.error_near("'}' expected"));
+            }
+            return Err(
+                self.error_near(format!("'}}' expected (to close '{{' at line {})", line).as_str())
+            );
+        }
+        Ok(())
+    }
+}
+
+// lexes and parses a chunk only to check that it is valid lua
+pub fn check_syntax(source: &str, version: LuaVersion) -> Result<(), ParseError> {
+    let tokens = lexer::tokenize(source)?;
+    // the lexer reads `/* */` comments in every dialect
+    if let Some(comment) = tokens.iter().find(|token| {
+        version != LuaVersion::Cfx
+            && token.kind == TokenKind::Comment
+            && token.text.starts_with("/*")
+    }) {
+        return Err(ParseError {
+            message: "unexpected symbol near '/'".to_string(),
+            line: comment.line,
+            column: comment.column,
+        });
+    }
+    let tokens: Vec<Token> = tokens
+        .into_iter()
+        .filter(|token| token.kind != TokenKind::Comment)
+        .collect();
+    parse(&tokens, version).map(|_| ())
+}
+
+// parses a chunk and resolves every name to the local it refers to, `tokens` must not contain
+// comments and must end with an eof token
+pub fn parse(tokens: &[Token], version: LuaVersion) -> Result<ScopeInfo, ParseError> {
+    // `goto` is a regular name before lua 5.2
+    let lua51_tokens: Vec<Token>;
+    let tokens = match version {
+        LuaVersion::Lua54 | LuaVersion::Cfx => tokens,
+        LuaVersion::Lua51 => {
+            lua51_tokens = tokens
+                .iter()
+                .cloned()
+                .map(|mut token| {
+                    if token.is_keyword("goto") {
+                        token.kind = TokenKind::Name;
+                    }
+                    token
+                })
+                .collect();
+            &lua51_tokens
+        }
+    };
+    let mut parser = Parser {
+        tokens,
+        version,
+        position: 0,
+        scopes: Vec::new(),
+        info: ScopeInfo {
+            resolutions: vec![None; tokens.len()],
+            ..Default::default()
+        },
+    };
+
+    parser.open_scope();
+    parser.block()?;
+    if parser.current().kind != TokenKind::Eof {
+        return Err(parser.error_near("'<eof>' expected"));
+    }
+    parser.close_scope();
+
+    Ok(parser.info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_source(source: &str, version: LuaVersion) -> (Vec<Token>, ScopeInfo) {
+        let tokens: Vec<Token> = lexer::tokenize(source)
+            .unwrap()
+            .into_iter()
+            .filter(|token| token.kind != TokenKind::Comment)
+            .collect();
+        let info = parse(&tokens, version).unwrap();
+        (tokens, info)
+    }
+
+    // the resolution of every token with the given text, in source order
+    fn resolutions_of(tokens: &[Token], info: &ScopeInfo, text: &str) -> Vec<Option<usize>> {
+        tokens
+            .iter()
+            .enumerate()
+            .filter(|(_, token)| token.text == text)
+            .map(|(i, _)| info.resolutions[i])
+            .collect()
+    }
+
+    #[test]
+    fn local_initializer_is_resolved_before_the_new_local() {
+        let (tokens, info) = parse_source("local x = 1 do local x = x end", LuaVersion::Lua54);
+        let x = resolutions_of(&tokens, &info, "x");
+        assert_eq!(x.len(), 3);
+        assert!(x[0].is_some() && x[1].is_some());
+        assert_ne!(x[0], x[1]);
+        // the `x` on the right refers to the outer local
+        assert_eq!(x[2], x[0]);
+    }
+
+    #[test]
+    fn shadowed_locals_end_with_their_block() {
+        let (tokens, info) = parse_source(
+            "local a = 1 do local a = 2 print(a) end print(a)",
+            LuaVersion::Lua54,
+        );
+        let a = resolutions_of(&tokens, &info, "a");
+        assert_eq!(a[2], a[1]);
+        assert_eq!(a[3], a[0]);
+        assert_eq!(info.globals, vec!["print".to_string()]);
+    }
+
+    #[test]
+    fn table_keys_and_fields_are_not_resolved() {
+        let (tokens, info) = parse_source(
+            "local key = 1 local t = { key = key } return t.key, t:key()",
+            LuaVersion::Lua54,
+        );
+        let key = resolutions_of(&tokens, &info, "key");
+        assert_eq!(key.len(), 5);
+        assert!(key[0].is_some());
+        // `{ key = ...}`, `t.key` and `t:key`
+        assert_eq!(key[1], None);
+        assert_eq!(key[2], key[0]);
+        assert_eq!(key[3], None);
+        assert_eq!(key[4], None);
+    }
+
+    #[test]
+    fn labels_and_goto_depend_on_the_version() {
+        let source = "::top:: goto top";
+        assert!(check_syntax(source, LuaVersion::Lua54).is_ok());
+        assert!(check_syntax(source, LuaVersion::Lua51).is_err());
+        // `goto` is a regular name in 5.1
+        let (tokens, info) = parse_source("local goto = 1 print(goto)", LuaVersion::Lua51);
+        let goto = resolutions_of(&tokens, &info, "goto");
+        assert!(goto[0].is_some());
+        assert_eq!(goto[1], goto[0]);
+    }
+
+    #[test]
+    fn attributes_and_operators_depend_on_the_version() {
+        let attributes = "local x <const>, y <close> = 1, nil";
+        assert!(check_syntax(attributes, LuaVersion::Lua54).is_ok());
+        assert!(check_syntax(attributes, LuaVersion::Lua51).is_err());
+        for operator in [
+            "a // b", "a & b", "a | b", "a ~ b", "~a", "a << b", "a >> b",
+        ] {
+            let source = format!("return {}", operator);
+            assert!(
+                check_syntax(&source, LuaVersion::Lua54).is_ok(),
+                "{}",
+                source
+            );
+            assert!(
+                check_syntax(&source, LuaVersion::Lua51).is_err(),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn cfx_accepts_the_cfxlua_extensions() {
+        let source = "local a = `WEAPON_PISTOL` a += 1 a <<= 2 t.x ..= 3";
+        let error = check_syntax(source, LuaVersion::Cfx).unwrap_err();
+        // `..=` is not one of the compound assignments
+        assert_eq!(error.message, "syntax error near '..'");
+        for source in [
+            "local a = `WEAPON_PISTOL`",
+            "a += 1",
+            "t[1] ^= 2",
+            "a.b <<= `x`",
+            "return a?.b?.c, a?[1]?.d, a?.b:c()",
+            "local x, y in point",
+            "local set = { .a, .b, c = 1 }",
+            "local a = 1 /* comment */ return a",
+            "/* a\ncomment\n*/ return 1",
+        ] {
+            assert!(check_syntax(source, LuaVersion::Cfx).is_ok(), "{}", source);
+            assert!(
+                check_syntax(source, LuaVersion::Lua54).is_err(),
+                "{}",
+                source
+            );
+        }
+        assert!(check_syntax("f() += 1", LuaVersion::Cfx).is_err());
+        assert!(check_syntax("a, b += 1", LuaVersion::Cfx).is_err());
+        assert!(check_syntax("local a = `unfinished", LuaVersion::Cfx).is_err());
+
+        assert!(check_syntax("/* unfinished", LuaVersion::Cfx).is_err());
+        let error = check_syntax("local a\n/* comment */", LuaVersion::Lua54).unwrap_err();
+        assert_eq!((error.line, error.column), (2, 1));
+
+        let (tokens, info) = parse_source("local a = 1 a += a", LuaVersion::Cfx);
+        let a = resolutions_of(&tokens, &info, "a");
+        assert_eq!(a, vec![a[0]; 3]);
+
+        // the names of unpacked locals are read, so they must keep them
+        let (_, info) = parse_source("local x, y in point local z = 1", LuaVersion::Cfx);
+        let keeps_name: Vec<bool> = info.bindings.iter().map(|b| b.keeps_name).collect();
+        assert_eq!(keeps_name, vec![true, true, false]);
+    }
+
+    #[test]
+    fn reports_the_position_of_syntax_errors() {
+        let error = check_syntax("local a = 1\nlocal b = = 2", LuaVersion::Lua54).unwrap_err();
+        assert_eq!((error.line, error.column), (2, 11));
+    }
+}
@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+
+const DIRECTIVE_PREFIX: &str = "--#";
+const DIRECTIVES: [&str; 4] = ["if", "elseif", "else", "end"];
+
+#[derive(Debug)]
+pub struct PreprocessError {
+    pub message: String,
+    pub line: usize,
+}
+
+struct Branch {
+    line: usize,
+    parent_active: bool,
+    taken: bool,
+    active: bool,
+    has_else: bool,
+}
+
+// a define is true unless it is missing or explicitly set to "false" or "0"
+fn is_truthy(defines: &HashMap<String, String>, name: &str) -> bool {
+    match defines.get(name) {
+        None => false,
+        Some(value) => value != "false" && value != "0",
+    }
+}
+
+fn evaluate_term(
+    term: &str,
+    defines: &HashMap<String, String>,
+    line: usize,
+) -> Result<bool, PreprocessError> {
+    let term = term.trim();
+    if let Some(name) = term.strip_prefix("not ") {
+        return evaluate_term(name, defines, line).map(|value| !value);
+    }
+    for (operator, equal) in [("==", true), ("~=", false)] {
+        if let Some((name, value)) = term.split_once(operator) {
+            let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+            let matches = defines.get(name.trim()).is_some_and(|v| v == value);
+            return Ok(matches == equal);
+        }
+    }
+    if term.is_empty() || !term.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return Err(PreprocessError {
+            message: format!("invalid condition '{}'", term),
+            line,
+        });
+    }
+    Ok(is_truthy(defines, term))
+}
+
+// conditions are `NAME`, `not NAME`, `NAME == value` or `NAME ~= value` joined with `and`/`or`,
+// `and` binds tighter than `or` like in lua
+fn evaluate(
+    condition: &str,
+    defines: &HashMap<String, String>,
+    line: usize,
+) -> Result<bool, PreprocessError> {
+    for alternative in condition.split(" or ") {
+        let mut all = true;
+        for term in alternative.split(" and ") {
+            all &= evaluate_term(term, defines, line)?;
+        }
+        if all {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+// strips the branches of `--#if` blocks whose condition is false. Removed lines are left empty so
+// line numbers in the output still match the source file
+pub fn preprocess(
+    source: &str,
+    defines: &HashMap<String, String>,
+) -> Result<String, PreprocessError> {
+    let mut branches: Vec<Branch> = Vec::new();
+    let mut output = String::with_capacity(source.len());
+
+    for (i, source_line) in source.split_inclusive('\n').enumerate() {
+        let line = i + 1;
+        let active = branches.last().is_none_or(|branch| branch.active);
+        let directive = source_line
+            .trim()
+            .strip_prefix(DIRECTIVE_PREFIX)
+            .map(|directive| directive.split_once(' ').unwrap_or((directive, "")))
+            .filter(|(keyword, _)| DIRECTIVES.contains(keyword));
+        // anything else, including comments like `--#region`, is kept as it is
+        let (keyword, condition) = match directive {
+            Some(directive) => directive,
+            None => {
+                if active {
+                    output.push_str(source_line);
+                } else if source_line.ends_with('\n') {
+                    output.push('\n');
+                }
+                continue;
+            }
+        };
+        let unmatched = |keyword: &str| PreprocessError {
+            message: format!("'--#{}' without a matching '--#if'", keyword),
+            line,
+        };
+
+        match keyword {
+            "if" => {
+                let taken = active && evaluate(condition, defines, line)?;
+                branches.push(Branch {
+                    line,
+                    parent_active: active,
+                    taken,
+                    active: taken,
+                    has_else: false,
+                });
+            }
+            "elseif" => {
+                let branch = branches.last_mut().ok_or_else(|| unmatched(keyword))?;
+                if branch.has_else {
+                    return Err(PreprocessError {
+                        message: "'--#elseif' after '--#else'".to_string(),
+                        line,
+                    });
+                }
+                branch.active =
+                    branch.parent_active && !branch.taken && evaluate(condition, defines, line)?;
+                branch.taken |= branch.active;
+            }
+            "else" => {
+                let branch = branches.last_mut().ok_or_else(|| unmatched(keyword))?;
+                if branch.has_else {
+                    return Err(PreprocessError {
+                        message: "duplicated '--#else'".to_string(),
+                        line,
+                    });
+                }
+                branch.has_else = true;
+                branch.active = branch.parent_active && !branch.taken;
+                branch.taken = true;
+            }
+            _ => {
+                branches.pop().ok_or_else(|| unmatched(keyword))?;
+            }
+        }
+        if source_line.ends_with('\n') {
+            output.push('\n');
+        }
+    }
+
+    match branches.last() {
+        Some(branch) => Err(PreprocessError {
+            message: "'--#if' is never closed with '--#end'".to_string(),
+            line: branch.line,
+        }),
+        None => Ok(output),
+    }
+}
@@ -0,0 +1,83 @@
+use std::{path::PathBuf, process::Command, time::Duration};
+
+use crate::{
+    builder::build_step::BuildStep,
+    logger,
+    process::{self, Interruption},
+};
+
+#[derive(Debug)]
+pub struct ExecStep {
+    pub name: String,
+    pub command: String,
+    pub args: Vec<String>,
+    pub working_dir: PathBuf,
+    // set on top of the inherited variables, later entries override earlier ones
+    pub env: Vec<(String, String)>,
+    // --timeout is used if this is not set
+    pub timeout: Option<Duration>,
+    // exit codes that count as a successful run, usually just 0
+    pub exit_codes: Vec<i32>,
+}
+
+impl BuildStep for ExecStep {
+    fn build(&self, resource_name: &String) -> Result<(), ()> {
+        let prefix = format!("[{}/{}]", resource_name, &self.name);
+        let command_line = std::iter::once(&self.command)
+            .chain(&self.args)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(" ");
+        logger::log_info(format!("{} Running \"{}\"", prefix, command_line).as_str());
+        let timeout = self.timeout.or(process::default_timeout());
+
+        let output = match process::run(
+            Command::new(&self.command)
+                .args(&self.args)
+                .current_dir(&self.working_dir)
+                .envs(self.env.iter().cloned()),
+            &prefix,
+            process::deadline(timeout),
+        ) {
+            Ok(output) => output,
+            Err(error) => {
+                logger::log_error(
+                    format!("{} Failed to run \"{}\": {}", prefix, command_line, error).as_str(),
+                );
+                return Err(());
+            }
+        };
+
+        match output.interrupted {
+            Some(Interruption::TimedOut) => {
+                output.replay(&prefix);
+                logger::log_error(
+                    format!(
+                        "{} \"{}\" timed out after {}s",
+                        prefix,
+                        command_line,
+                        timeout.unwrap_or_default().as_secs_f64()
+                    )
+                    .as_str(),
+                );
+                return Err(());
+            }
+            Some(Interruption::Cancelled) => {
+                logger::log_warn(format!("{} Cancelled \"{}\"", prefix, command_line).as_str());
+                return Err(());
+            }
+            None => {}
+        }
+        // a process killed by a signal has no exit code
+        match output.status.code() {
+            Some(code) if self.exit_codes.contains(&code) => Ok(()),
+            _ => {
+                output.replay(&prefix);
+                logger::log_error(
+                    format!("{} \"{}\" failed ({})", prefix, command_line, output.status).as_str(),
+                );
+                Err(())
+            }
+        }
+    }
+}
@@ -0,0 +1,107 @@
+// parses the contents of a dotenv file, lines look like `KEY=value`, `export KEY="value"` or
+// `# comment`. Values are not interpolated, but quotes around them are removed
+pub fn parse(contents: &str) -> Result<Vec<(String, String)>, String> {
+    let mut variables = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(format!("expected KEY=value at line {}", index + 1));
+        };
+        let key = key.trim();
+        if key.is_empty() || key.chars().any(char::is_whitespace) {
+            return Err(format!("invalid variable name at line {}", index + 1));
+        }
+        let value = value.trim();
+        let quoted = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => value[1..].split_once(quote).filter(|(_, rest)| {
+                // only a comment can follow the closing quote
+                let rest = rest.trim_start();
+                rest.is_empty() || rest.starts_with('#')
+            }),
+            _ => None,
+        };
+        let value = match quoted {
+            Some((value, _)) => value,
+            // unquoted values can be followed by a comment
+            None => value.split(" #").next().unwrap_or_default().trim_end(),
+        };
+        variables.push((key.to_string(), value.to_string()));
+    }
+    Ok(variables)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_ok(contents: &str) -> Vec<(String, String)> {
+        parse(contents).unwrap()
+    }
+
+    fn variable(key: &str, value: &str) -> (String, String) {
+        (key.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn reads_plain_and_exported_variables() {
+        let contents = "# comment\n\nA=1\nexport B = two words \nC=\n";
+        assert_eq!(
+            parse_ok(contents),
+            vec![
+                variable("A", "1"),
+                variable("B", "two words"),
+                variable("C", "")
+            ]
+        );
+    }
+
+    #[test]
+    fn removes_quotes_and_keeps_what_is_inside() {
+        let contents = "A=\"a # not a comment\"\nB='single'\nC=\"it's\"\nD=\"\"";
+        assert_eq!(
+            parse_ok(contents),
+            vec![
+                variable("A", "a # not a comment"),
+                variable("B", "single"),
+                variable("C", "it's"),
+                variable("D", ""),
+            ]
+        );
+    }
+
+    #[test]
+    fn strips_comments_after_values() {
+        let contents = "A=value # comment\nB=\"quoted\" # comment\nC='quoted'#comment\nD=a#b";
+        assert_eq!(
+            parse_ok(contents),
+            vec![
+                variable("A", "value"),
+                variable("B", "quoted"),
+                variable("C", "quoted"),
+                variable("D", "a#b"),
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_unterminated_quotes() {
+        assert_eq!(parse_ok("A=\"open"), vec![variable("A", "\"open")]);
+        assert_eq!(parse_ok("A=\"a\" b"), vec![variable("A", "\"a\" b")]);
+    }
+
+    #[test]
+    fn reports_invalid_lines() {
+        assert_eq!(
+            parse("A=1\nnot a variable").unwrap_err(),
+            "expected KEY=value at line 2"
+        );
+        assert_eq!(
+            parse("MY KEY=1").unwrap_err(),
+            "invalid variable name at line 1"
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use crate::logger;
+
+// lives inside node_modules, so deleting node_modules also forces a new install
+const INSTALL_HASH_FILE: &str = "node_modules/.jade_install_hash";
+const HASHED_FILES: [&str; 6] = [
+    "package.json",
+    "pnpm-lock.yaml",
+    "yarn.lock",
+    "bun.lockb",
+    "bun.lock",
+    "package-lock.json",
+];
+
+// the result of the install of each folder, shared by every step and resource in this run
+type InstallResult = Arc<Mutex<Option<Result<(), ()>>>>;
+static INSTALLS: OnceLock<Mutex<HashMap<PathBuf, InstallResult>>> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InstallOptions {
+    // install even when the dependencies did not change since the last install
+    pub force: bool,
+    // install exactly what the lockfile says and fail if it is out of date, meant for CI
+    pub frozen: bool,
+}
+
+// 64 bit FNV-1a, unlike the std hasher its output never changes between rust versions
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+// hash of the package manager, package.json and the lockfiles of `folder`, and the package.json
+// of the workspace `members` if `folder` is the root of a workspace
+pub fn dependencies_hash(folder: &Path, members: &[PathBuf], package_manager: &str) -> String {
+    let mut hash = fnv1a(0xcbf29ce484222325, package_manager.as_bytes());
+    let files = HASHED_FILES
+        .iter()
+        .map(|file| folder.join(file))
+        .chain(members.iter().map(|member| member.join("package.json")));
+    for file in files {
+        if let Ok(contents) = std::fs::read(&file) {
+            let name = file.strip_prefix(folder).unwrap_or(&file);
+            hash = fnv1a(hash, name.to_string_lossy().as_bytes());
+            hash = fnv1a(hash, &contents);
+        }
+    }
+    format!("{:016x}", hash)
+}
+
+// runs `install` the first time it is called for `folder`, later calls wait for it to finish
+// and get its result instead of installing into the same node_modules again
+pub fn install_once(
+    folder: &Path,
+    prefix: &str,
+    install: impl FnOnce() -> Result<(), ()>,
+) -> Result<(), ()> {
+    let result = INSTALLS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap()
+        .entry(folder.to_path_buf())
+        .or_default()
+        .clone();
+    // held during the install, so steps installing into the same folder run one at a time
+    let mut result = result.lock().unwrap();
+    match *result {
+        Some(Ok(())) => {
+            logger::log_info(
+                format!(
+                    "{} Dependencies were already installed by another step",
+                    prefix
+                )
+                .as_str(),
+            );
+            Ok(())
+        }
+        Some(Err(())) => {
+            logger::log_error(
+                format!("{} Dependencies failed to install in another step", prefix).as_str(),
+            );
+            Err(())
+        }
+        None => {
+            let installed = install();
+            *result = Some(installed);
+            installed
+        }
+    }
+}
+
+pub fn is_up_to_date(folder: &Path, hash: &str) -> bool {
+    std::fs::read_to_string(folder.join(INSTALL_HASH_FILE))
+        .is_ok_and(|stored| stored.trim() == hash)
+}
+
+pub fn store_hash(folder: &Path, hash: &str) {
+    let _ = std::fs::write(folder.join(INSTALL_HASH_FILE), hash);
+}
+
+// a failed install can leave node_modules half written, so the old hash must not survive it
+pub fn clear_hash(folder: &Path) {
+    let _ = std::fs::remove_file(folder.join(INSTALL_HASH_FILE));
+}
+
+// the arguments that install dependencies, `name` is the package manager without path or
+// extension. Frozen installs return None for managers that are not known to support them
+pub fn install_args(name: &str, folder: &Path, frozen: bool) -> Option<Vec<&'static str>> {
+    if !frozen {
+        return Some(vec!["install"]);
+    }
+    match name {
+        "npm" => Some(vec!["ci"]),
+        "pnpm" | "bun" => Some(vec!["install", "--frozen-lockfile"]),
+        // yarn 2+ is configured through .yarnrc.yml and renamed the flag
+        "yarn" if folder.join(".yarnrc.yml").is_file() => Some(vec!["install", "--immutable"]),
+        "yarn" => Some(vec!["install", "--frozen-lockfile"]),
+        _ => None,
+    }
+}
@@ -1,74 +1,322 @@
-use std::{path::PathBuf, process::Command};
+mod env_file;
+mod install;
+mod workspace;
 
-use crate::{builder::build_step::BuildStep, logger};
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    builder::{build_metadata::BuildMetadata, build_step::BuildStep},
+    logger,
+    process::{self, Interruption},
+};
+pub use install::InstallOptions;
+use workspace::Workspace;
 
 pub const DEFAULT_PACKAGE_MANAGER: &str = "npm";
+// checked in order, the first lockfile found in the folder decides the package manager
+const LOCKFILES: [(&str, &str); 5] = [
+    ("pnpm-lock.yaml", "pnpm"),
+    ("yarn.lock", "yarn"),
+    ("bun.lockb", "bun"),
+    ("bun.lock", "bun"),
+    ("package-lock.json", "npm"),
+];
 
 #[derive(Debug)]
 pub struct JSBuildStep {
     pub name: String,
-    pub package_manager: String,
+    // set through <package_manager> or --package-manager, detected from the lockfile otherwise
+    pub package_manager: Option<String>,
     pub folder: PathBuf,
     pub build_script: String,
     pub install_packages: bool,
+    pub install_options: InstallOptions,
+    // from <env> children, placeholders like `{env}` are already expanded
+    pub env: Vec<(String, String)>,
+    pub env_file: Option<PathBuf>,
+    pub metadata: BuildMetadata,
+    // covers the install and the build script, --timeout is used if this is not set
+    pub timeout: Option<Duration>,
 }
 
-impl BuildStep for JSBuildStep {
-    fn build(&self, resource_name: &String) {
-        let path = dunce::canonicalize(&self.folder).unwrap();
+// the lockfile in `folder` and the package manager that created it
+fn detect_package_manager(folder: &Path) -> Option<(&'static str, &'static str)> {
+    LOCKFILES
+        .into_iter()
+        .find(|(lockfile, _)| folder.join(lockfile).is_file())
+}
 
-        if self.install_packages {
-            logger::log_info(
-                format!(
-                    "[{}/{}] Installing dependencies with {}",
-                    resource_name, &self.name, &self.package_manager
-                )
-                .as_str(),
-            );
-            match Command::new(&self.package_manager)
-                .current_dir(&path)
-                .arg("install")
-                .output()
-            {
-                Ok(_) => {}
+impl JSBuildStep {
+    fn resolve_package_manager(&self, resource_name: &String, folder: &Path) -> String {
+        let detected = detect_package_manager(folder);
+        match (&self.package_manager, detected) {
+            (None, Some((_, package_manager))) => package_manager.to_string(),
+            (None, None) => DEFAULT_PACKAGE_MANAGER.to_string(),
+            (Some(configured), Some((lockfile, package_manager))) => {
+                // the configured manager may be a path or have an extension like `npm.cmd`
+                let name = Path::new(configured)
+                    .file_stem()
+                    .map(|name| name.to_string_lossy().to_string());
+                if name.as_deref() != Some(package_manager) {
+                    logger::log_warn(
+                        format!(
+                            "[{}/{}] Using {} but the folder contains {}, which belongs to {}",
+                            resource_name, &self.name, configured, lockfile, package_manager
+                        )
+                        .as_str(),
+                    );
+                }
+                configured.clone()
+            }
+            (Some(configured), None) => configured.clone(),
+        }
+    }
+
+    // the variables passed to the package manager on top of the inherited ones, later entries
+    // override earlier ones
+    fn environment(&self, resource_name: &String) -> Result<Vec<(String, String)>, ()> {
+        let mut variables = self.metadata.environment_variables();
+        if let Some(env_file) = &self.env_file {
+            let contents = match std::fs::read_to_string(env_file) {
+                Ok(contents) => contents,
                 Err(error) => {
                     logger::log_error(
                         format!(
-                            "[{}/{}] Failed to install dependencies with {}: {}",
-                            resource_name, &self.name, &self.package_manager, error
+                            "[{}/{}] Could not read env file {}: {}",
+                            resource_name,
+                            &self.name,
+                            env_file.display(),
+                            error
                         )
                         .as_str(),
                     );
-                    return;
+                    return Err(());
+                }
+            };
+            match env_file::parse(&contents) {
+                Ok(file_variables) => variables.extend(
+                    file_variables
+                        .into_iter()
+                        .map(|(key, value)| (key, self.metadata.expand(&value))),
+                ),
+                Err(error) => {
+                    logger::log_error(
+                        format!(
+                            "[{}/{}] Invalid env file {}: {}",
+                            resource_name,
+                            &self.name,
+                            env_file.display(),
+                            error
+                        )
+                        .as_str(),
+                    );
+                    return Err(());
                 }
             }
         }
+        variables.extend(self.env.iter().cloned());
+        Ok(variables)
+    }
+
+    fn install(
+        &self,
+        resource_name: &String,
+        package_manager: &str,
+        path: &Path,
+        environment: &[(String, String)],
+        deadline: Option<Instant>,
+    ) -> Result<(), ()> {
+        let prefix = format!("[{}/{}]", resource_name, &self.name);
+        // `path` is the root of a workspace both when the folder is a member and when the folder
+        // is the root itself, the members are part of the hash either way
+        let members = Workspace::at_root(path)
+            .map(|workspace| workspace.members())
+            .unwrap_or_default();
+        let hash = install::dependencies_hash(path, &members, package_manager);
+        if !self.install_options.force && install::is_up_to_date(path, &hash) {
+            logger::log_info(
+                format!("{} Dependencies are up to date, skipping install", prefix).as_str(),
+            );
+            return Ok(());
+        }
+
+        let name = Path::new(package_manager)
+            .file_stem()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let args = match install::install_args(&name, path, self.install_options.frozen) {
+            Some(args) => args,
+            None => {
+                logger::log_warn(
+                    format!(
+                        "{} Frozen installs are not supported for {}, running a regular install",
+                        prefix, package_manager
+                    )
+                    .as_str(),
+                );
+                vec!["install"]
+            }
+        };
 
         logger::log_info(
             format!(
-                "[{}/{}] Running \"{}\" with {}",
-                resource_name, &self.name, &self.build_script, &self.package_manager
+                "{} Installing dependencies with {} {}",
+                prefix,
+                package_manager,
+                args.join(" ")
             )
             .as_str(),
         );
+        install::clear_hash(path);
+        self.run(
+            resource_name,
+            package_manager,
+            Command::new(package_manager)
+                .current_dir(path)
+                .args(args)
+                .envs(environment.iter().cloned()),
+            "install dependencies",
+            deadline,
+        )?;
+        // installing can rewrite the lockfile, so the stored hash is taken after it
+        install::store_hash(
+            path,
+            &install::dependencies_hash(path, &members, package_manager),
+        );
+        Ok(())
+    }
 
-        match Command::new(&self.package_manager)
-            .current_dir(&path)
-            .arg("run")
-            .arg(&self.build_script)
-            .output()
-        {
-            Ok(_) => {}
+    // `action` describes the command in error messages, e.g. "install dependencies"
+    fn run(
+        &self,
+        resource_name: &String,
+        package_manager: &str,
+        command: &mut Command,
+        action: &str,
+        deadline: Option<Instant>,
+    ) -> Result<(), ()> {
+        let prefix = format!("[{}/{}]", resource_name, &self.name);
+        match process::run(command, &prefix, deadline) {
+            Ok(output) if output.interrupted == Some(Interruption::Cancelled) => {
+                logger::log_warn(
+                    format!("{} Cancelled while trying to {}", prefix, action).as_str(),
+                );
+                Err(())
+            }
+            Ok(output) if output.interrupted == Some(Interruption::TimedOut) => {
+                output.replay(&prefix);
+                logger::log_error(
+                    format!(
+                        "{} Timed out after {}s while trying to {}",
+                        prefix,
+                        self.timeout
+                            .or(process::default_timeout())
+                            .unwrap_or_default()
+                            .as_secs_f64(),
+                        action
+                    )
+                    .as_str(),
+                );
+                Err(())
+            }
+            Ok(output) if output.status.success() => Ok(()),
+            Ok(output) => {
+                output.replay(&prefix);
+                logger::log_error(
+                    format!(
+                        "{} Failed to {} with {} ({})",
+                        prefix, action, package_manager, output.status
+                    )
+                    .as_str(),
+                );
+                Err(())
+            }
+            Err(error) => {
+                logger::log_error(
+                    format!(
+                        "{} Failed to {} with {}: {}",
+                        prefix, action, package_manager, error
+                    )
+                    .as_str(),
+                );
+                Err(())
+            }
+        }
+    }
+}
+
+impl BuildStep for JSBuildStep {
+    fn build(&self, resource_name: &String) -> Result<(), ()> {
+        let path = match dunce::canonicalize(&self.folder) {
+            Ok(path) => path,
             Err(error) => {
                 logger::log_error(
                     format!(
-                        "[{}/{}] Failed to run \"{}\" with {}: {}",
-                        resource_name, &self.name, &self.build_script, &self.package_manager, error
+                        "[{}/{}] Could not open the folder {}: {}",
+                        resource_name,
+                        &self.name,
+                        self.folder.display(),
+                        error
+                    )
+                    .as_str(),
+                );
+                return Err(());
+            }
+        };
+        // members of a workspace are installed from its root, where the lockfile is
+        let workspace = Workspace::find(&path);
+        let install_path = workspace
+            .as_ref()
+            .map_or(path.clone(), |workspace| workspace.root.clone());
+        let package_manager = self.resolve_package_manager(resource_name, &install_path);
+        let environment = self.environment(resource_name)?;
+        let deadline = process::deadline(self.timeout.or(process::default_timeout()));
+
+        if self.install_packages {
+            let prefix = format!("[{}/{}]", resource_name, &self.name);
+            if workspace.is_some() {
+                logger::log_info(
+                    format!(
+                        "{} Installing dependencies from the workspace at {}",
+                        prefix,
+                        install_path.display()
                     )
                     .as_str(),
                 );
-                return;
             }
+            install::install_once(&install_path, &prefix, || {
+                self.install(
+                    resource_name,
+                    &package_manager,
+                    &install_path,
+                    &environment,
+                    deadline,
+                )
+            })?;
         }
+
+        logger::log_info(
+            format!(
+                "[{}/{}] Running \"{}\" with {}",
+                resource_name, &self.name, &self.build_script, &package_manager
+            )
+            .as_str(),
+        );
+
+        self.run(
+            resource_name,
+            &package_manager,
+            Command::new(&package_manager)
+                .current_dir(&path)
+                .arg("run")
+                .arg(&self.build_script)
+                .envs(environment),
+            format!("run \"{}\"", &self.build_script).as_str(),
+            deadline,
+        )
     }
 }
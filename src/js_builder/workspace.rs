@@ -0,0 +1,146 @@
+use std::path::{Path, PathBuf};
+
+// directories that never contain workspace members
+const IGNORED_DIRS: [&str; 2] = ["node_modules", ".git"];
+
+// a pnpm, npm, yarn or bun workspace, members share the node_modules and lockfile of the root
+#[derive(Debug)]
+pub struct Workspace {
+    pub root: PathBuf,
+    // globs relative to the root, e.g. `packages/*`, patterns starting with `!` exclude members
+    patterns: Vec<String>,
+}
+
+// the `packages` list of a pnpm-workspace.yaml, only the plain list syntax is supported
+fn pnpm_patterns(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .skip_while(|line| line.trim_end() != "packages:")
+        .skip(1)
+        .take_while(|line| line.trim().is_empty() || line.starts_with([' ', '\t', '-']))
+        .filter_map(|line| line.trim().strip_prefix('-'))
+        .map(|pattern| pattern.trim().trim_matches(['"', '\'']).to_string())
+        .collect()
+}
+
+// the `workspaces` field of a package.json, either a list or `{ "packages": [...] }`
+fn package_json_patterns(contents: &str) -> Option<Vec<String>> {
+    let package = json::parse(contents).ok()?;
+    let workspaces = &package["workspaces"];
+    let patterns = if workspaces.is_array() {
+        workspaces
+    } else {
+        &workspaces["packages"]
+    };
+    if !patterns.is_array() {
+        return None;
+    }
+    Some(
+        patterns
+            .members()
+            .filter_map(|pattern| pattern.as_str().map(str::to_string))
+            .collect(),
+    )
+}
+
+fn workspace_patterns(dir: &Path) -> Option<Vec<String>> {
+    if let Ok(contents) = std::fs::read_to_string(dir.join("pnpm-workspace.yaml")) {
+        return Some(pnpm_patterns(&contents));
+    }
+    package_json_patterns(&std::fs::read_to_string(dir.join("package.json")).ok()?)
+}
+
+// matches a single path segment against a pattern segment where `*` matches any characters
+fn matches_segment(pattern: &str, segment: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == segment,
+        Some((prefix, rest)) => {
+            let Some(segment) = segment.strip_prefix(prefix) else {
+                return false;
+            };
+            (0..=segment.len())
+                .filter(|index| segment.is_char_boundary(*index))
+                .any(|index| matches_segment(rest, &segment[index..]))
+        }
+    }
+}
+
+// `**` matches any number of segments
+fn matches_path(pattern: &[&str], path: &[&str]) -> bool {
+    match (pattern.first(), path.first()) {
+        (None, _) => path.is_empty(),
+        (Some(&"**"), _) => {
+            matches_path(&pattern[1..], path)
+                || (!path.is_empty() && matches_path(pattern, &path[1..]))
+        }
+        (Some(_), None) => false,
+        (Some(pattern_segment), Some(segment)) => {
+            matches_segment(pattern_segment, segment) && matches_path(&pattern[1..], &path[1..])
+        }
+    }
+}
+
+fn segments(path: &str) -> Vec<&str> {
+    path.split('/')
+        .filter(|segment| !segment.is_empty() && *segment != ".")
+        .collect()
+}
+
+impl Workspace {
+    // the workspace whose root is `dir`, if it is one
+    pub fn at_root(dir: &Path) -> Option<Workspace> {
+        Some(Workspace {
+            root: dir.to_path_buf(),
+            patterns: workspace_patterns(dir)?,
+        })
+    }
+
+    // the closest workspace above `folder` that includes it as a member
+    pub fn find(folder: &Path) -> Option<Workspace> {
+        folder.ancestors().skip(1).find_map(|dir| {
+            let workspace = Workspace::at_root(dir)?;
+            workspace.contains(folder).then_some(workspace)
+        })
+    }
+
+    fn contains(&self, folder: &Path) -> bool {
+        let Ok(relative) = folder.strip_prefix(&self.root) else {
+            return false;
+        };
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        let path = segments(&relative);
+        let matches = |pattern: &str| matches_path(&segments(pattern), &path);
+        self.patterns
+            .iter()
+            .any(|pattern| !pattern.starts_with('!') && matches(pattern))
+            && !self
+                .patterns
+                .iter()
+                .filter_map(|pattern| pattern.strip_prefix('!'))
+                .any(matches)
+    }
+
+    // every folder below the root with a package.json that belongs to the workspace, sorted
+    pub fn members(&self) -> Vec<PathBuf> {
+        let mut members = Vec::new();
+        let mut pending = vec![self.root.clone()];
+        while let Some(dir) = pending.pop() {
+            let Ok(entries) = std::fs::read_dir(&dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let name = entry.file_name();
+                if !path.is_dir() || IGNORED_DIRS.iter().any(|ignored| name == *ignored) {
+                    continue;
+                }
+                if path.join("package.json").is_file() && self.contains(&path) {
+                    members.push(path.clone());
+                }
+                pending.push(path);
+            }
+        }
+        members.sort();
+        members
+    }
+}
@@ -1,14 +1,40 @@
 use colored::Colorize;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static USE_STDERR: AtomicBool = AtomicBool::new(false);
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+// used by commands that write their result to stdout, so logs do not end up mixed with it
+pub fn log_to_stderr() {
+    USE_STDERR.store(true, Ordering::Relaxed);
+}
+
+// in quiet mode the output of child processes is only shown when they fail
+pub fn set_quiet() {
+    QUIET.store(true, Ordering::Relaxed);
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+fn print(tag: impl std::fmt::Display, message: &str) {
+    if USE_STDERR.load(Ordering::Relaxed) {
+        eprintln!("{} {}", tag, message);
+    } else {
+        println!("{} {}", tag, message);
+    }
+}
 
 pub fn log_error(message: &str) {
-    println!("{} {}", "[ERROR]".red(), message);
+    print("[ERROR]".red(), message);
 }
 pub fn log_info(message: &str) {
-    println!("{} {}", "[ INFO]".bright_blue(), message);
+    print("[ INFO]".bright_blue(), message);
 }
 pub fn log_success(message: &str) {
-    println!("{} {}", "[   OK]".green(), message);
+    print("[   OK]".green(), message);
 }
 pub fn log_warn(message: &str) {
-    println!("{} {}", "[ WARN]".yellow(), message);
-}
\ No newline at end of file
+    print("[ WARN]".yellow(), message);
+}
@@ -1,14 +1,18 @@
 mod builder;
 mod bundler;
+mod exec_runner;
 mod js_builder;
 mod logger;
 mod manifest_generator;
 mod parallel_builder;
 mod path_resolver;
+mod process;
 
 use builder::build_config::BuildConfig;
-use clap::{arg, command, Arg};
-use js_builder::DEFAULT_PACKAGE_MANAGER;
+use bundler::bundler::Bundler;
+use bundler::graph::{self, GraphFormat, ModuleGraph};
+use clap::{arg, command, Arg, ArgAction, ArgMatches, Command};
+use js_builder::{InstallOptions, DEFAULT_PACKAGE_MANAGER};
 use path_resolver::{
     enumerate_buildable_resources, find_resources_dir, get_build_config_file,
     is_dir_a_buildable_resource,
@@ -18,6 +22,7 @@ use std::path::PathBuf;
 use std::process::exit;
 use std::sync::Arc;
 use std::thread::{self, JoinHandle};
+use std::time::Duration;
 
 const ASCII_LOGO: &str = r#"
 ⠀⠀⠀⠀⠀⠀⠀⠀⠀⡀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀
@@ -37,37 +42,10 @@ const ASCII_LOGO: &str = r#"
 ⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠁⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀
 "#;
 
-fn main() {
-    let matches = command!()
-        .arg(arg!(<RESOURCE> "Resource(s) to build").required(false))
-        .arg(
-            Arg::new("ENVIRONMENT")
-                .long("env")
-                .required(false)
-                .value_name("ENVIRONMENT")
-                .help("Selects the type of config file to build"),
-        )
-        .arg(
-            Arg::new("PACKAGE_MANAGER")
-                .long("package-manager")
-                .required(false)
-                .value_name("PACKAGE_MANAGER")
-                .help(format!(
-                    "Selects the package manager to use (default is '{}')",
-                    DEFAULT_PACKAGE_MANAGER
-                )),
-        )
-        .get_matches();
-
-    println!("{}", ASCII_LOGO);
-
-    let resource = matches.get_one::<String>("RESOURCE");
-    let environment = matches.get_one::<String>("ENVIRONMENT");
-    let package_manager: &String = match matches.get_one::<String>("PACKAGE_MANAGER") {
-        Some(package_manager) => package_manager,
-        None => &DEFAULT_PACKAGE_MANAGER.to_string(),
-    };
-
+fn find_resources(
+    resource: Option<&String>,
+    environment: Option<&String>,
+) -> HashMap<String, PathBuf> {
     let current_path = PathBuf::from(".").canonicalize().unwrap();
     let resources_path = find_resources_dir(&current_path);
     if resources_path.is_none() && (resource.is_none() || resource.unwrap() != ".") {
@@ -75,7 +53,7 @@ fn main() {
         exit(1);
     }
 
-    let resources = match resource {
+    match resource {
         // If no resource is specified, build all resources
         None => enumerate_buildable_resources(&resources_path.unwrap(), environment),
         Some(resource) => {
@@ -108,8 +86,172 @@ fn main() {
                 }
             }
         }
+    }
+}
+
+fn export_graph(matches: &ArgMatches, environment: Option<&String>) {
+    let resource = matches.get_one::<String>("RESOURCE");
+    let bundle = matches.get_one::<String>("BUNDLE");
+    let format = match matches.get_one::<String>("FORMAT").unwrap().as_str() {
+        "json" => GraphFormat::Json,
+        _ => GraphFormat::Dot,
     };
 
+    let (resource_name, resource_path) = find_resources(resource, environment)
+        .into_iter()
+        .next()
+        .unwrap();
+    let config = match BuildConfig::new(
+        resource_name.clone(),
+        resource_path,
+        None,
+        InstallOptions::default(),
+        environment,
+    ) {
+        Ok(config) => config,
+        Err(_) => exit(1),
+    };
+
+    let steps: Vec<_> = config
+        .bundle_steps()
+        .into_iter()
+        .filter(|step| bundle.is_none_or(|bundle| &step.name == bundle))
+        .collect();
+    if steps.is_empty() {
+        logger::log_error(format!("[{}] No bundle to export a graph for", &resource_name).as_str());
+        exit(1);
+    }
+
+    let mut graphs: Vec<ModuleGraph> = Vec::new();
+    for step in steps {
+        match Bundler::new(step, &resource_name) {
+            Ok(bundler) => graphs.push(bundler.graph(&step.name)),
+            Err(_) => exit(1),
+        }
+    }
+
+    let output = graph::render(&resource_name, &graphs, format);
+    match matches.get_one::<String>("OUTPUT") {
+        None => print!("{}", output),
+        Some(path) => std::fs::write(path, output).unwrap(),
+    }
+}
+
+fn main() {
+    let matches = command!()
+        .arg(arg!(<RESOURCE> "Resource(s) to build").required(false))
+        .arg(
+            Arg::new("ENVIRONMENT")
+                .long("env")
+                .required(false)
+                .value_name("ENVIRONMENT")
+                .global(true)
+                .help("Selects the type of config file to build"),
+        )
+        .arg(
+            Arg::new("PACKAGE_MANAGER")
+                .long("package-manager")
+                .required(false)
+                .value_name("PACKAGE_MANAGER")
+                .help(format!(
+                    "Selects the package manager to use (detected from the lockfile by default, '{}' if there is none)",
+                    DEFAULT_PACKAGE_MANAGER
+                )),
+        )
+        .arg(
+            Arg::new("FORCE_INSTALL")
+                .long("force-install")
+                .action(ArgAction::SetTrue)
+                .help("Installs JS dependencies even if they did not change since the last install"),
+        )
+        .arg(
+            Arg::new("FROZEN_INSTALL")
+                .long("frozen-install")
+                .action(ArgAction::SetTrue)
+                .help("Installs JS dependencies exactly as locked, failing if the lockfile is outdated"),
+        )
+        .arg(
+            Arg::new("TIMEOUT")
+                .long("timeout")
+                .value_name("SECONDS")
+                .value_parser(clap::value_parser!(f64))
+                .help("Kills the commands of a step that runs longer than this, unless the step sets its own <timeout>"),
+        )
+        .arg(
+            Arg::new("QUIET")
+                .long("quiet")
+                .short('q')
+                .action(ArgAction::SetTrue)
+                .help("Only shows the output of build commands when they fail"),
+        )
+        .subcommand(
+            Command::new("graph")
+                .about("Exports the require graph of the lua bundles of a resource")
+                .arg(arg!(<RESOURCE> "Resource to inspect"))
+                .arg(
+                    Arg::new("BUNDLE")
+                        .long("bundle")
+                        .required(false)
+                        .value_name("BUNDLE")
+                        .help("Only exports the bundle with this name"),
+                )
+                .arg(
+                    Arg::new("FORMAT")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .value_parser(["dot", "json"])
+                        .default_value("dot")
+                        .help("Output format of the graph"),
+                )
+                .arg(
+                    Arg::new("OUTPUT")
+                        .long("output")
+                        .short('o')
+                        .required(false)
+                        .value_name("FILE")
+                        .help("Writes the graph to a file instead of the standard output"),
+                ),
+        )
+        .get_matches();
+
+    let environment = matches.get_one::<String>("ENVIRONMENT");
+    // the graph goes to stdout, so nothing else is printed before it
+    if let Some(graph_matches) = matches.subcommand_matches("graph") {
+        logger::log_to_stderr();
+        export_graph(graph_matches, environment);
+        return;
+    }
+
+    println!("{}", ASCII_LOGO);
+
+    let resource = matches.get_one::<String>("RESOURCE");
+    if matches.get_flag("QUIET") {
+        logger::set_quiet();
+    }
+    let package_manager = matches.get_one::<String>("PACKAGE_MANAGER");
+    let install_options = InstallOptions {
+        force: matches.get_flag("FORCE_INSTALL"),
+        frozen: matches.get_flag("FROZEN_INSTALL"),
+    };
+    if let Some(timeout) = matches.get_one::<f64>("TIMEOUT") {
+        // same rules as <timeout>
+        match Duration::try_from_secs_f64(*timeout) {
+            Ok(timeout) if !timeout.is_zero() => process::set_default_timeout(timeout),
+            _ => {
+                logger::log_error(
+                    format!(
+                        "--timeout expected a positive number of seconds, found \"{}\"",
+                        timeout
+                    )
+                    .as_str(),
+                );
+                exit(1);
+            }
+        }
+    }
+
+    let resources = find_resources(resource, environment);
+
     let buildable_resources: Vec<Arc<BuildConfig>> = resources
         .iter()
         .filter_map(|(resource_name, resource_path)| {
@@ -117,6 +259,8 @@ fn main() {
                 resource_name.clone(),
                 resource_path.clone(),
                 package_manager,
+                install_options,
+                environment,
             ) {
                 Ok(config) => Some(Arc::new(config)),
                 Err(_) => {
@@ -133,19 +277,72 @@ fn main() {
         })
         .collect();
 
+    let invalid_resources = resources.len() - buildable_resources.len();
     logger::log_info(format!("Found {} resource(s) to build", buildable_resources.len()).as_str());
 
     let start_time = std::time::Instant::now();
 
-    let threads: Vec<JoinHandle<()>> = buildable_resources
+    // the first Ctrl-C stops the running commands so the build can report what it cancelled,
+    // a second one exits right away
+    let _ = ctrlc::set_handler(|| {
+        if process::is_cancelled() {
+            exit(130);
+        }
+        logger::log_warn("Cancelling build, press Ctrl-C again to exit immediately");
+        process::cancel();
+    });
+
+    let threads: Vec<(String, JoinHandle<Result<(), ()>>)> = buildable_resources
         .into_iter()
-        .map(|resource| thread::spawn(move || resource.build()))
+        .map(|resource| {
+            (
+                resource.name.clone(),
+                thread::spawn(move || resource.build()),
+            )
+        })
         .collect();
 
-    threads
+    // resources whose config could not be parsed count as failed too, and so do the ones whose
+    // build panicked
+    let failed = threads
         .into_iter()
-        .for_each(|handle| handle.join().unwrap());
+        .map(|(name, handle)| {
+            handle.join().unwrap_or_else(|_| {
+                logger::log_error(format!("[{}] Build failed", name).as_str());
+                Err(())
+            })
+        })
+        .filter(|result| result.is_err())
+        .count()
+        + invalid_resources;
 
     let duration = start_time.elapsed().as_secs_f64();
+    if process::is_cancelled() {
+        let cancelled = process::cancelled_processes();
+        logger::log_warn(
+            format!(
+                "Build cancelled after {:.2}s, {} running step(s) were stopped{}",
+                duration,
+                cancelled.len(),
+                if cancelled.is_empty() {
+                    String::new()
+                } else {
+                    format!(": {}", cancelled.join(", "))
+                }
+            )
+            .as_str(),
+        );
+        exit(130);
+    }
+    if failed > 0 {
+        logger::log_error(
+            format!(
+                "Build failed for {} resource(s) after {:.2}s!",
+                failed, duration
+            )
+            .as_str(),
+        );
+        exit(1);
+    }
     logger::log_success(format!("Build finished in {:.2}s!", duration).as_str());
 }
@@ -23,7 +23,7 @@ pub struct ManifestGenerationStep {
 }
 
 impl BuildStep for ManifestGenerationStep {
-    fn build(&self, resource_name: &String) {
+    fn build(&self, resource_name: &String) -> Result<(), ()> {
         logger::log_info(
             format!("[{}/fx_manifest] Generating fxmanifest.lua", resource_name).as_str(),
         );
@@ -103,5 +103,10 @@ impl BuildStep for ManifestGenerationStep {
         }
 
         writeln!(file).unwrap();
+        Ok(())
+    }
+
+    fn manifest_steps(&self) -> Vec<&ManifestGenerationStep> {
+        vec![self]
     }
 }
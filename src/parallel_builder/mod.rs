@@ -1,4 +1,6 @@
 use crate::builder::build_step::BuildStep;
+use crate::bundler::bundle_step::BundleStep;
+use crate::manifest_generator::ManifestGenerationStep;
 use std::{
     sync::Arc,
     thread::{self, JoinHandle},
@@ -10,16 +12,35 @@ pub struct ParallelBuildStep {
 }
 
 impl BuildStep for ParallelBuildStep {
-    fn build(&self, resource_name: &String) {
-        self.steps
+    // every step runs to the end even if another one fails, the step fails if any of them did
+    fn build(&self, resource_name: &String) -> Result<(), ()> {
+        let results: Vec<Result<(), ()>> = self
+            .steps
             .iter()
             .map(|step| {
                 let step = Arc::clone(step);
                 let resource_name = resource_name.clone();
                 thread::spawn(move || step.build(&resource_name))
             })
-            .collect::<Vec<JoinHandle<()>>>()
+            .collect::<Vec<JoinHandle<Result<(), ()>>>>()
             .into_iter()
-            .for_each(|handle| handle.join().unwrap());
+            // a step that panicked failed
+            .map(|handle| handle.join().unwrap_or(Err(())))
+            .collect();
+        results.into_iter().collect()
+    }
+
+    fn bundle_steps(&self) -> Vec<&BundleStep> {
+        self.steps
+            .iter()
+            .flat_map(|step| step.bundle_steps())
+            .collect()
+    }
+
+    fn manifest_steps(&self) -> Vec<&ManifestGenerationStep> {
+        self.steps
+            .iter()
+            .flat_map(|step| step.manifest_steps())
+            .collect()
     }
 }
@@ -0,0 +1,196 @@
+use crate::logger;
+use std::{
+    io::{self, BufRead, BufReader, Read},
+    process::{Child, Command, ExitStatus, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+// the prefixes of the processes that were killed because the build was cancelled
+static CANCELLED_PROCESSES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+static DEFAULT_TIMEOUT: Mutex<Option<Duration>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Stream {
+    Stdout,
+    Stderr,
+}
+
+// why a process was killed before it exited on its own
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Interruption {
+    TimedOut,
+    Cancelled,
+}
+
+type OutputBuffer = Arc<Mutex<Vec<(Stream, String)>>>;
+
+#[derive(Debug)]
+pub struct ProcessOutput {
+    pub status: ExitStatus,
+    pub interrupted: Option<Interruption>,
+    // only filled in quiet mode, in the order the lines were written
+    buffered: Vec<(Stream, String)>,
+}
+
+fn log_line(prefix: &str, stream: Stream, line: &str) {
+    let message = format!("{} {}", prefix, line);
+    match stream {
+        Stream::Stdout => logger::log_info(message.as_str()),
+        Stream::Stderr => logger::log_warn(message.as_str()),
+    }
+}
+
+// reads `reader` line by line, lines are logged as they arrive or buffered in quiet mode
+fn forward<R: Read + Send + 'static>(
+    reader: R,
+    stream: Stream,
+    prefix: String,
+    buffer: Option<OutputBuffer>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(reader);
+        let mut line = Vec::new();
+        while reader
+            .read_until(b'\n', &mut line)
+            .is_ok_and(|read| read > 0)
+        {
+            let text = String::from_utf8_lossy(&line).trim_end().to_string();
+            match &buffer {
+                Some(buffer) => buffer.lock().unwrap().push((stream, text)),
+                None => log_line(&prefix, stream, &text),
+            }
+            line.clear();
+        }
+    })
+}
+
+// stops every running process and makes the ones started afterwards exit right away
+pub fn cancel() {
+    CANCELLED.store(true, Ordering::Relaxed);
+}
+
+pub fn is_cancelled() -> bool {
+    CANCELLED.load(Ordering::Relaxed)
+}
+
+pub fn cancelled_processes() -> Vec<String> {
+    CANCELLED_PROCESSES.lock().unwrap().clone()
+}
+
+// used by steps that don't configure their own timeout
+pub fn set_default_timeout(timeout: Duration) {
+    *DEFAULT_TIMEOUT.lock().unwrap() = Some(timeout);
+}
+
+pub fn default_timeout() -> Option<Duration> {
+    *DEFAULT_TIMEOUT.lock().unwrap()
+}
+
+// when a step started now with `timeout` runs out of time, a timeout too far away to be
+// represented never runs out
+pub fn deadline(timeout: Option<Duration>) -> Option<Instant> {
+    Instant::now().checked_add(timeout?)
+}
+
+// package managers start processes of their own, which would keep running and holding the
+// output pipes open if only the direct child was killed
+#[cfg(unix)]
+fn kill_tree(child: &mut Child) {
+    // the child leads its own process group, see `run`
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+}
+
+#[cfg(windows)]
+fn kill_tree(child: &mut Child) {
+    let killed = Command::new("taskkill")
+        .args(["/T", "/F", "/PID", child.id().to_string().as_str()])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success());
+    if !killed {
+        let _ = child.kill();
+    }
+}
+
+impl ProcessOutput {
+    // logs the lines that quiet mode held back, e.g. after the process failed
+    pub fn replay(&self, prefix: &str) {
+        for (stream, line) in &self.buffered {
+            log_line(prefix, *stream, line);
+        }
+    }
+}
+
+// runs `command` and streams its stdout and stderr through the logger, every line prefixed with
+// `prefix`. The process gets no stdin, so it can not wait for input. If it is still running at
+// `deadline` or when the build is cancelled, it is killed together with the processes it started
+pub fn run(
+    command: &mut Command,
+    prefix: &str,
+    deadline: Option<Instant>,
+) -> io::Result<ProcessOutput> {
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    // a group of its own lets kill_tree reach the processes it starts, and keeps the terminal
+    // from sending them Ctrl-C before jade can report what it cancelled
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(command, 0);
+    let mut child = command.spawn()?;
+
+    let buffer = logger::is_quiet().then(|| Arc::new(Mutex::new(Vec::new())));
+    let readers = [
+        forward(
+            child.stdout.take().unwrap(),
+            Stream::Stdout,
+            prefix.to_string(),
+            buffer.clone(),
+        ),
+        forward(
+            child.stderr.take().unwrap(),
+            Stream::Stderr,
+            prefix.to_string(),
+            buffer.clone(),
+        ),
+    ];
+    let mut interrupted = None;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if is_cancelled() {
+            interrupted = Some(Interruption::Cancelled);
+        } else if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            interrupted = Some(Interruption::TimedOut);
+        }
+        if interrupted.is_some() {
+            kill_tree(&mut child);
+            break child.wait()?;
+        }
+        thread::sleep(Duration::from_millis(20));
+    };
+    if interrupted == Some(Interruption::Cancelled) {
+        CANCELLED_PROCESSES.lock().unwrap().push(prefix.to_string());
+    }
+    for reader in readers {
+        reader.join().unwrap();
+    }
+
+    Ok(ProcessOutput {
+        status,
+        interrupted,
+        buffered: buffer
+            .map(|buffer| std::mem::take(&mut *buffer.lock().unwrap()))
+            .unwrap_or_default(),
+    })
+}